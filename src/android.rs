@@ -0,0 +1,575 @@
+//! Android string resources (`res/values*/strings.xml`).
+//!
+//! All categories share one resource directory, so resource names are
+//! namespaced as `<category>__<message>` with attributes suffixed as
+//! `<category>__<message>__<attribute>`. Resources without a namespace, as
+//! found in hand-written apps, are loaded into a category named after their
+//! file.
+//!
+//! Message ids are written as they are, except that dashes, which resource
+//! names can't contain, become underscores. Names are read back unchanged, so
+//! `foo_bar` and `fooBar` round trip while `foo-bar` comes back as `foo_bar`.
+
+use std::{collections::BTreeMap, path::Path, str::FromStr};
+
+use fluent_syntax::parser::ParserError;
+use heck::{ToSnakeCase, ToTitleCase};
+use icu::locid::{locale, LanguageIdentifier};
+use quick_xml::events::Event;
+
+use crate::{
+    flt::render::{self, Segment},
+    ir::{Category, Keys, Pattern, Project, TUIdentifier, TranslationUnit, TranslationUnitMap},
+    plural::{self, PluralSelect},
+    printf, PathNode,
+};
+
+pub enum Resource {
+    String {
+        name: String,
+        description: Option<String>,
+        value: String,
+    },
+    Plurals {
+        name: String,
+        description: Option<String>,
+        items: Vec<(String, String)>,
+    },
+}
+
+/// The resource qualifier for a locale, e.g. `de`, `pt-rBR` or `b+sr+Latn`.
+fn qualifier(locale: &LanguageIdentifier) -> String {
+    if locale.script.is_some() || !locale.variants.is_empty() {
+        format!("b+{}", locale.to_string().replace('-', "+"))
+    } else if let Some(region) = locale.region {
+        format!("{}-r{}", locale.language, region)
+    } else {
+        locale.language.to_string()
+    }
+}
+
+pub(crate) fn resource_name(
+    category: &str,
+    key: &TUIdentifier,
+    attr: Option<&TUIdentifier>,
+) -> String {
+    match attr {
+        Some(attr) => format!("{}__{}__{}", category, name_part(key), name_part(attr)),
+        None => format!("{}__{}", category, name_part(key)),
+    }
+}
+
+/// Resource names can't contain dashes, so those are the one part of an
+/// identifier that doesn't survive.
+fn name_part(key: &TUIdentifier) -> String {
+    key.replace('-', "_")
+}
+
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\'' => out.push_str("\\'"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn format_segments(segments: &[Segment], args: &mut Vec<String>, numbers: &[&str]) -> String {
+    let out = printf::format(segments, args, numbers, printf::ANDROID, escape);
+
+    if out.starts_with('@') || out.starts_with('?') {
+        format!("\\{out}")
+    } else {
+        out
+    }
+}
+
+fn make_resource(
+    name: String,
+    description: Option<String>,
+    pattern: &Pattern,
+    units: &TranslationUnitMap,
+    args: &mut Vec<String>,
+) -> Resource {
+    match PluralSelect::from_pattern(pattern) {
+        Some(select) => {
+            let items = plural::cardinal_categories(&units.locale)
+                .iter()
+                .map(|category| {
+                    let pattern = select.expand(category);
+                    let segments = render::segments(&pattern, units);
+                    (
+                        category.to_string(),
+                        format_segments(&segments, args, &[select.variable]),
+                    )
+                })
+                .collect();
+            Resource::Plurals {
+                name,
+                description,
+                items,
+            }
+        }
+        None => {
+            let segments = render::segments(pattern, units);
+            Resource::String {
+                name,
+                description,
+                value: format_segments(&segments, args, &[]),
+            }
+        }
+    }
+}
+
+fn write_comment(out: &mut String, description: &Option<String>) {
+    if let Some(description) = description {
+        out.push_str(&format!(
+            "    <!-- {} -->\n",
+            description.trim().replace("--", "- -")
+        ));
+    }
+}
+
+fn write_resources(resources: &[Resource]) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<resources>\n");
+
+    for resource in resources {
+        match resource {
+            Resource::String {
+                name,
+                description,
+                value,
+            } => {
+                write_comment(&mut out, description);
+                out.push_str(&format!("    <string name=\"{name}\">{value}</string>\n"));
+            }
+            Resource::Plurals {
+                name,
+                description,
+                items,
+            } => {
+                write_comment(&mut out, description);
+                out.push_str(&format!("    <plurals name=\"{name}\">\n"));
+                for (quantity, value) in items {
+                    out.push_str(&format!(
+                        "        <item quantity=\"{quantity}\">{value}</item>\n"
+                    ));
+                }
+                out.push_str("    </plurals>\n");
+            }
+        }
+    }
+
+    out.push_str("</resources>\n");
+    out
+}
+
+pub fn generate(input: Project) -> Result<PathNode, ParserError> {
+    let mut dirs: BTreeMap<String, Vec<Resource>> = BTreeMap::new();
+
+    for (category_id, category) in input.categories.into_iter() {
+        let base = category.base_strings();
+
+        for locale in category.ordered_locale_keys() {
+            let units = category.get(locale).unwrap();
+            let dir = if *locale == category.default_locale {
+                "values".to_string()
+            } else {
+                format!("values-{}", qualifier(locale))
+            };
+            let resources = dirs.entry(dir).or_default();
+
+            for (key, unit) in units.iter() {
+                // Terms cannot be referenced from app code; they are inlined
+                // wherever messages use them.
+                if unit.is_term() {
+                    continue;
+                }
+
+                let description = category.descriptions.get(key).cloned();
+                let values = std::iter::once((None, &unit.main))
+                    .chain(unit.attributes.iter().map(|(k, v)| (Some(k), v)));
+
+                for (attr, value) in values {
+                    let mut args = printf::arguments(base, key, attr);
+
+                    resources.push(make_resource(
+                        resource_name(&category_id, key, attr),
+                        description.clone(),
+                        value,
+                        units,
+                        &mut args,
+                    ));
+                }
+            }
+        }
+    }
+
+    let files = dirs
+        .into_iter()
+        .map(|(dir, resources)| {
+            let mut files = BTreeMap::new();
+            files.insert(
+                "strings.xml".to_string(),
+                PathNode::File(write_resources(&resources).into_bytes()),
+            );
+            (dir, PathNode::Directory(files))
+        })
+        .collect();
+
+    Ok(PathNode::Directory(files))
+}
+
+/// The locale for a resource directory such as `values-pt-rBR`, or `None` if
+/// the directory has qualifiers other than a locale (e.g. `values-night`).
+fn parse_qualifier(dir: &str, default_locale: &LanguageIdentifier) -> Option<LanguageIdentifier> {
+    let qualifier = dir.strip_prefix("values")?;
+    if qualifier.is_empty() {
+        return Some(default_locale.clone());
+    }
+
+    let qualifier = qualifier.strip_prefix('-')?;
+    if let Some(bcp47) = qualifier.strip_prefix("b+") {
+        return LanguageIdentifier::from_str(&bcp47.replace('+', "-")).ok();
+    }
+
+    let mut parts = qualifier.split('-');
+    let language = parts.next()?;
+    if !(2..=3).contains(&language.len()) || !language.chars().all(|x| x.is_ascii_lowercase()) {
+        return None;
+    }
+
+    let tag = match parts.next() {
+        Some(region) => format!("{}-{}", language, region.strip_prefix('r')?),
+        None => language.to_string(),
+    };
+
+    if parts.next().is_some() {
+        return None;
+    }
+
+    LanguageIdentifier::from_str(&tag).ok()
+}
+
+/// Resolves Android's string escaping and quoting rules to the literal text.
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.trim().chars();
+    let mut quoted = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('u') => {
+                    let hex = chars.by_ref().take(4).collect::<String>();
+                    match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                        Some(c) => out.push(c),
+                        None => out.push(char::REPLACEMENT_CHARACTER),
+                    }
+                }
+                Some(c) => out.push(c),
+                None => {}
+            },
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !out.ends_with(' ') {
+                    out.push(' ');
+                }
+            }
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+fn attribute(e: &quick_xml::events::BytesStart, name: &str) -> anyhow::Result<Option<String>> {
+    Ok(match e.try_get_attribute(name)? {
+        Some(x) => Some(x.unescape_value()?.to_string()),
+        None => None,
+    })
+}
+
+/// Parses the `<string>` and `<plurals>` entries of a resource file. Comments
+/// directly preceding an entry become its description.
+pub fn parse_resources(xml: &str) -> anyhow::Result<Vec<Resource>> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut resources = vec![];
+
+    let mut comment: Option<String> = None;
+    // The resource being read and the quantity of the plural item being read
+    let mut current: Option<Resource> = None;
+    let mut quantity: Option<String> = None;
+    let mut text = String::new();
+
+    loop {
+        match reader.read_event()? {
+            Event::Eof => break,
+            Event::Comment(e) if current.is_none() => {
+                comment = Some(std::str::from_utf8(&e)?.trim().to_string());
+            }
+            Event::Start(e) => match (e.name().as_ref(), &current) {
+                (b"string", None) => {
+                    let Some(name) = attribute(&e, "name")? else {
+                        continue;
+                    };
+                    if attribute(&e, "translatable")?.as_deref() == Some("false") {
+                        continue;
+                    }
+                    text.clear();
+                    current = Some(Resource::String {
+                        name,
+                        description: comment.take(),
+                        value: String::new(),
+                    });
+                }
+                (b"plurals", None) => {
+                    let Some(name) = attribute(&e, "name")? else {
+                        continue;
+                    };
+                    current = Some(Resource::Plurals {
+                        name,
+                        description: comment.take(),
+                        items: vec![],
+                    });
+                }
+                (b"item", Some(Resource::Plurals { .. })) => {
+                    text.clear();
+                    quantity = attribute(&e, "quantity")?;
+                }
+                // Placeholder annotations only wrap the argument itself
+                (b"xliff:g", Some(_)) => {}
+                (name, Some(_)) => {
+                    text.push('<');
+                    text.push_str(std::str::from_utf8(name)?);
+                    text.push('>');
+                }
+                _ => comment = None,
+            },
+            Event::End(e) => match (e.name().as_ref(), current.as_mut()) {
+                (b"string", Some(Resource::String { value, .. })) => {
+                    *value = std::mem::take(&mut text);
+                    resources.extend(current.take());
+                }
+                (b"item", Some(Resource::Plurals { items, .. })) => {
+                    if let Some(quantity) = quantity.take() {
+                        items.push((quantity, std::mem::take(&mut text)));
+                    }
+                }
+                (b"plurals", Some(Resource::Plurals { .. })) => {
+                    resources.extend(current.take());
+                }
+                (b"xliff:g", Some(_)) => {}
+                (name, Some(_)) => {
+                    text.push_str("</");
+                    text.push_str(std::str::from_utf8(name)?);
+                    text.push('>');
+                }
+                _ => {}
+            },
+            Event::Text(e) if current.is_some() => text.push_str(&e.unescape()?),
+            Event::CData(e) if current.is_some() => text.push_str(std::str::from_utf8(&e)?),
+            Event::Empty(_) => comment = None,
+            _ => {}
+        }
+    }
+
+    Ok(resources)
+}
+
+/// The select of a `<plurals>` resource. Android picks the item by the
+/// quantity passed to `getQuantityString`, which apps also pass as the
+/// format's integer argument, so that argument is what the select is on.
+fn plurals_pattern(items: Vec<(String, String)>) -> Pattern {
+    let items = items
+        .into_iter()
+        .map(|(quantity, value)| (quantity, unescape(&value)))
+        .collect::<Vec<_>>();
+    let other = items
+        .iter()
+        .find(|(quantity, _)| quantity == "other")
+        .or(items.last());
+    let arg = other
+        .and_then(|(_, value)| printf::number_argument(value))
+        .unwrap_or(1);
+
+    let items = items
+        .into_iter()
+        .map(|(quantity, value)| (quantity, printf::to_pattern(&value)))
+        .collect();
+    plural::select(&format!("arg{arg}"), items)
+}
+
+/// Splits a resource name into its category, message and attribute.
+fn split_name<'a>(
+    name: &'a str,
+    fallback_category: &'a str,
+) -> (&'a str, &'a str, Option<&'a str>) {
+    let mut chunks = name.splitn(3, "__");
+    match (chunks.next(), chunks.next(), chunks.next()) {
+        (Some(category), Some(key), attr) => (category, key, attr),
+        _ => (fallback_category, name, None),
+    }
+}
+
+/// Loads every `values*/strings.xml` file of an Android `res/` directory.
+pub fn load_project_from_path(path: &Path, keys: &mut Keys) -> anyhow::Result<Project> {
+    let default_locale: LanguageIdentifier = locale!("en").id;
+    let mut project = Project {
+        default_locale: Some(default_locale.clone()),
+        ..Default::default()
+    };
+
+    let mut dirs = path
+        .read_dir()?
+        .filter_map(Result::ok)
+        .filter(|x| x.path().join("strings.xml").is_file())
+        .filter_map(|x| {
+            let dir = x.file_name().to_str()?.to_string();
+            match parse_qualifier(&dir, &default_locale) {
+                Some(locale) => Some((locale, x.path().join("strings.xml"))),
+                None => {
+                    eprintln!("[{}] Not a locale qualifier; skipping", dir);
+                    None
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // Descriptions are taken from the default locale where there are any
+    dirs.sort_by_key(|(locale, _)| *locale != default_locale);
+
+    for (locale, file) in dirs {
+        let xml = std::fs::read_to_string(&file)?;
+        let file_stem = file.file_stem().and_then(|x| x.to_str()).unwrap();
+
+        for resource in parse_resources(&xml)? {
+            let (name, description, value) = match resource {
+                Resource::String {
+                    name,
+                    description,
+                    value,
+                } => (name, description, printf::to_pattern(&unescape(&value))),
+                Resource::Plurals {
+                    name,
+                    description,
+                    items,
+                } => (name, description, plurals_pattern(items)),
+            };
+
+            let (category_id, key, attr) = split_name(&name, file_stem);
+            let category_id = keys.category(&category_id.to_snake_case())?;
+            let key = keys.unit(&category_id, key)?;
+
+            let category = project
+                .entry(category_id.clone())
+                .or_insert_with(|| Category {
+                    key: category_id.clone(),
+                    name: category_id.to_title_case(),
+                    default_locale: default_locale.clone(),
+                    descriptions: Default::default(),
+                    notes: None,
+                    sections: Default::default(),
+                    translation_units: Default::default(),
+                });
+
+            if let Some(description) = description.filter(|_| attr.is_none()) {
+                category
+                    .descriptions
+                    .entry(key.clone())
+                    .or_insert(description);
+            }
+
+            let unit = category
+                .entry(locale.clone())
+                .or_insert_with(|| TranslationUnitMap::new(locale.clone()))
+                .entry(key.clone())
+                .or_insert_with(|| TranslationUnit::new(key.clone()));
+
+            match attr {
+                Some(attr) => {
+                    let attr = keys.attribute(&category_id, &key, attr)?;
+                    unit.attributes.insert(attr, value);
+                }
+                None => unit.main = value,
+            }
+        }
+    }
+
+    // Every category needs its base strings, even if it only showed up in
+    // translated resources.
+    for category in project.values_mut() {
+        category
+            .entry(default_locale.clone())
+            .or_insert_with(|| TranslationUnitMap::new(default_locale.clone()));
+    }
+
+    Ok(project)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plurals_round_trip() {
+        let xml = concat!(
+            "<resources>\n",
+            "    <plurals name=\"core__files\">\n",
+            "        <item quantity=\"one\">%1$d file in %2$s</item>\n",
+            "        <item quantity=\"other\">%1$d files in %2$s</item>\n",
+            "    </plurals>\n",
+            "</resources>\n",
+        );
+        let Resource::Plurals { items, .. } = parse_resources(xml).unwrap().remove(0) else {
+            unreachable!()
+        };
+
+        let key = TUIdentifier::try_from("files").unwrap();
+        let mut units = TranslationUnitMap::new(locale!("en").id);
+        let mut unit = TranslationUnit::new(key.clone());
+        unit.main = plurals_pattern(items.clone());
+        units.insert(key.clone(), unit);
+
+        let mut args = printf::arguments(&units, &key, None);
+        let resource = make_resource(
+            "core__files".to_string(),
+            None,
+            &units[&key].main,
+            &units,
+            &mut args,
+        );
+        let Resource::Plurals {
+            items: exported, ..
+        } = resource
+        else {
+            unreachable!()
+        };
+        assert_eq!(exported, items);
+    }
+
+    #[test]
+    fn reordered_arguments_round_trip() {
+        let value = "%2$s shared %1$d files";
+        let key = TUIdentifier::try_from("shared").unwrap();
+        let mut units = TranslationUnitMap::new(locale!("en").id);
+        let mut unit = TranslationUnit::new(key.clone());
+        unit.main = printf::to_pattern(value);
+        units.insert(key.clone(), unit);
+
+        let mut args = printf::arguments(&units, &key, None);
+        let segments = render::segments(&units[&key].main, &units);
+        assert_eq!(format_segments(&segments, &mut args, &[]), value);
+    }
+}
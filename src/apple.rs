@@ -0,0 +1,255 @@
+//! Legacy Apple `.strings` and `.stringsdict` files, for targets that cannot
+//! use String Catalogs (see [`crate::xcstrings`]).
+//!
+//! Each category becomes a table in every `<locale>.lproj` directory. Plural
+//! messages are written to the `.stringsdict` table only.
+
+use std::collections::BTreeMap;
+
+use fluent_syntax::parser::ParserError;
+use heck::{ToLowerCamelCase, ToPascalCase};
+
+use crate::{
+    flt::render,
+    ir::{Project, TUIdentifier},
+    plural::{self, PluralSelect},
+    printf, PathNode,
+};
+
+fn string_key(key: &TUIdentifier, attr: Option<&TUIdentifier>) -> String {
+    match attr {
+        Some(attr) => format!("{}__{}", key, attr),
+        None => key.to_string(),
+    }
+}
+
+fn escape_strings(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+}
+
+fn escape_xml(value: &str) -> String {
+    html_escape::encode_text(value).to_string()
+}
+
+struct PluralEntry {
+    key: String,
+    /// Position of the selector in the argument list, starting at 1
+    position: usize,
+    variable: String,
+    variants: Vec<(&'static str, String)>,
+}
+
+fn write_strings(entries: &[(String, Option<String>, String)]) -> String {
+    let mut out = String::new();
+    for (key, description, value) in entries {
+        if let Some(description) = description {
+            out.push_str(&format!(
+                "/* {} */\n",
+                description.trim().replace("*/", "* /")
+            ));
+        }
+        out.push_str(&format!(
+            "\"{}\" = \"{}\";\n\n",
+            escape_strings(key),
+            escape_strings(value)
+        ));
+    }
+    out
+}
+
+fn write_stringsdict(entries: &[PluralEntry]) -> String {
+    let mut out = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" ",
+        "\"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n",
+        "<plist version=\"1.0\">\n<dict>\n"
+    ));
+
+    for entry in entries {
+        out.push_str(&format!(
+            "    <key>{}</key>\n    <dict>\n",
+            escape_xml(&entry.key)
+        ));
+        out.push_str("        <key>NSStringLocalizedFormatKey</key>\n");
+        out.push_str(&format!(
+            "        <string>%{}$#@{}@</string>\n",
+            entry.position, entry.variable
+        ));
+        out.push_str(&format!(
+            "        <key>{}</key>\n        <dict>\n",
+            entry.variable
+        ));
+        out.push_str("            <key>NSStringFormatSpecTypeKey</key>\n");
+        out.push_str("            <string>NSStringPluralRuleType</string>\n");
+        out.push_str("            <key>NSStringFormatValueTypeKey</key>\n");
+        out.push_str(&format!(
+            "            <string>{}</string>\n",
+            printf::APPLE.number
+        ));
+        for (category, value) in entry.variants.iter() {
+            out.push_str(&format!("            <key>{category}</key>\n"));
+            out.push_str(&format!(
+                "            <string>{}</string>\n",
+                escape_xml(value)
+            ));
+        }
+        out.push_str("        </dict>\n    </dict>\n");
+    }
+
+    out.push_str("</dict>\n</plist>\n");
+    out
+}
+
+pub fn generate(input: Project) -> Result<PathNode, ParserError> {
+    let mut lprojs: BTreeMap<String, BTreeMap<String, PathNode>> = BTreeMap::new();
+
+    for (category_id, category) in input.categories.into_iter() {
+        let base = category.base_strings();
+        let table = category_id.to_pascal_case();
+
+        for locale in category.ordered_locale_keys() {
+            let units = category.get(locale).unwrap();
+            let mut strings = vec![];
+            let mut plurals = vec![];
+
+            for (key, attr) in category.ordered_tu_identity_keys() {
+                // Terms only exist to be inlined into messages
+                if base[key].is_term() {
+                    continue;
+                }
+
+                let Some(value) = units.get(key).and_then(|x| x.value(attr)) else {
+                    continue;
+                };
+
+                let mut args = printf::arguments(base, key, attr);
+
+                match PluralSelect::from_pattern(value) {
+                    Some(select) => {
+                        let variants = plural::cardinal_categories(locale)
+                            .iter()
+                            .map(|category| {
+                                let segments = render::segments(&select.expand(category), units);
+                                let value = printf::format(
+                                    &segments,
+                                    &mut args,
+                                    &[select.variable],
+                                    printf::APPLE,
+                                    str::to_string,
+                                );
+                                (*category, value)
+                            })
+                            .collect();
+                        let position = render::argument_index(&mut args, select.variable) + 1;
+
+                        plurals.push(PluralEntry {
+                            key: string_key(key, attr),
+                            position,
+                            variable: select.variable.to_lower_camel_case(),
+                            variants,
+                        });
+                    }
+                    None => {
+                        let segments = render::segments(value, units);
+                        let value = printf::format(
+                            &segments,
+                            &mut args,
+                            &[],
+                            printf::APPLE,
+                            str::to_string,
+                        );
+                        strings.push((
+                            string_key(key, attr),
+                            category.descriptions.get(key).cloned(),
+                            value,
+                        ));
+                    }
+                }
+            }
+
+            let files = lprojs.entry(format!("{locale}.lproj")).or_default();
+            files.insert(
+                format!("{table}.strings"),
+                PathNode::File(write_strings(&strings).into_bytes()),
+            );
+            if !plurals.is_empty() {
+                files.insert(
+                    format!("{table}.stringsdict"),
+                    PathNode::File(write_stringsdict(&plurals).into_bytes()),
+                );
+            }
+        }
+    }
+
+    Ok(PathNode::Directory(
+        lprojs
+            .into_iter()
+            .map(|(k, v)| (k, PathNode::Directory(v)))
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flt::test_project;
+
+    #[test]
+    fn strings_escaping() {
+        let entries = vec![(
+            "quote".to_string(),
+            Some("Ends a */ comment".to_string()),
+            "Say \"hi\"\\\n\tnow".to_string(),
+        )];
+        assert_eq!(
+            write_strings(&entries),
+            "/* Ends a * / comment */\n\"quote\" = \"Say \\\"hi\\\"\\\\\\n\\tnow\";\n\n"
+        );
+    }
+
+    #[test]
+    fn stringsdict_plurals() {
+        let project = test_project(&[(
+            "en",
+            concat!(
+                "files = { $count ->\n",
+                "    [one] One file in { $folder }\n",
+                "   *[other] { $count } files in { $folder }\n",
+                "}\n",
+            ),
+        )]);
+        let tree = generate(project).unwrap();
+
+        assert_eq!(tree.text("en.lproj/Core.strings"), "");
+        assert_eq!(
+            tree.text("en.lproj/Core.stringsdict"),
+            concat!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+                "<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" ",
+                "\"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n",
+                "<plist version=\"1.0\">\n<dict>\n",
+                "    <key>files</key>\n",
+                "    <dict>\n",
+                "        <key>NSStringLocalizedFormatKey</key>\n",
+                "        <string>%1$#@count@</string>\n",
+                "        <key>count</key>\n",
+                "        <dict>\n",
+                "            <key>NSStringFormatSpecTypeKey</key>\n",
+                "            <string>NSStringPluralRuleType</string>\n",
+                "            <key>NSStringFormatValueTypeKey</key>\n",
+                "            <string>lld</string>\n",
+                "            <key>one</key>\n",
+                "            <string>One file in %2$@</string>\n",
+                "            <key>other</key>\n",
+                "            <string>%1$lld files in %2$@</string>\n",
+                "        </dict>\n",
+                "    </dict>\n",
+                "</dict>\n</plist>\n",
+            )
+        );
+    }
+}
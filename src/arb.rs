@@ -0,0 +1,366 @@
+//! Flutter Application Resource Bundles (`app_<locale>.arb`), with an
+//! optional typed Dart class along the lines of [`crate::ts`].
+//!
+//! Messages are written in ICU MessageFormat by [`crate::messageformat`], so
+//! the generated `l10n.yaml` turns on `use-escaping`. Message keys are
+//! `<category><Key><Attribute>` in lowerCamelCase, as ARB keys must be valid
+//! Dart identifiers.
+
+use std::collections::BTreeMap;
+
+use heck::{ToLowerCamelCase, ToPascalCase};
+use serde_json::{Map, Value};
+
+use crate::{
+    flt::render,
+    ir::{Category, Pattern, Project, TUIdentifier, TranslationUnitMap},
+    messageformat::{self, MessageFormatError},
+    PathNode,
+};
+
+fn message_key(category: &str, key: &TUIdentifier, attr: Option<&TUIdentifier>) -> String {
+    match attr {
+        Some(attr) => format!("{}_{}_{}", category, key, attr),
+        None => format!("{}_{}", category, key),
+    }
+    .to_lower_camel_case()
+}
+
+fn arb_locale(units: &TranslationUnitMap) -> String {
+    units.locale.to_string().replace('-', "_")
+}
+
+/// A placeholder of a message, named as in the MessageFormat string.
+struct Placeholder {
+    name: String,
+    kind: render::VariableKind,
+}
+
+impl Placeholder {
+    /// The type, which is the same in ARB metadata and Dart.
+    fn type_name(&self) -> &'static str {
+        match self.kind {
+            render::VariableKind::String => "String",
+            render::VariableKind::Number => "num",
+        }
+    }
+}
+
+fn placeholders(value: &Pattern, units: &TranslationUnitMap) -> Vec<Placeholder> {
+    render::variables(value, units)
+        .into_iter()
+        .map(|(name, kind)| Placeholder {
+            name: messageformat::argument_name(&name),
+            kind,
+        })
+        .collect()
+}
+
+fn metadata(description: Option<&String>, placeholders: &[Placeholder]) -> Value {
+    let mut out = Map::new();
+    if let Some(description) = description {
+        out.insert("description".into(), Value::String(description.clone()));
+    }
+    if !placeholders.is_empty() {
+        out.insert(
+            "placeholders".into(),
+            Value::Object(
+                placeholders
+                    .iter()
+                    .map(|x| {
+                        let mut placeholder = Map::new();
+                        placeholder.insert("type".into(), Value::String(x.type_name().into()));
+                        (x.name.clone(), Value::Object(placeholder))
+                    })
+                    .collect(),
+            ),
+        );
+    }
+    Value::Object(out)
+}
+
+/// Adds a category's messages to the per-locale ARB maps. Metadata is only
+/// written for the category's default locale, which acts as the template.
+fn add_category(
+    category_id: &str,
+    category: &Category,
+    locales: &mut BTreeMap<String, Map<String, Value>>,
+    unsupported: &mut Vec<(String, String, String)>,
+) {
+    let base = category.base_strings();
+
+    for (key, attr) in category.ordered_tu_identity_keys() {
+        // Terms only exist to be inlined into messages
+        if base[key].is_term() {
+            continue;
+        }
+
+        let arb_key = message_key(category_id, key, attr);
+
+        for (_, units) in category.translation_units.iter() {
+            let Some(value) = units.get(key).and_then(|x| x.value(attr)) else {
+                continue;
+            };
+
+            let message = match messageformat::convert_without_pound(value, units) {
+                Ok(x) => x,
+                Err(reason) => {
+                    unsupported.push((arb_key.clone(), units.locale.to_string(), reason));
+                    continue;
+                }
+            };
+
+            let arb = locales.entry(arb_locale(units)).or_insert_with(|| {
+                let mut map = Map::new();
+                map.insert("@@locale".into(), Value::String(arb_locale(units)));
+                map
+            });
+            arb.insert(arb_key.clone(), Value::String(message));
+
+            if units.locale == category.default_locale {
+                let placeholders = placeholders(value, base);
+                let description = category.descriptions.get(key);
+                if description.is_some() || !placeholders.is_empty() {
+                    arb.insert(format!("@{arb_key}"), metadata(description, &placeholders));
+                }
+            }
+        }
+    }
+}
+
+fn dart_string(value: &str) -> String {
+    format!(
+        "'{}'",
+        value
+            .replace('\\', "\\\\")
+            .replace('\'', "\\'")
+            .replace('$', "\\$")
+    )
+}
+
+fn generate_dart_class(category_id: &str, category: &Category) -> String {
+    let base = category.base_strings();
+    let mut out = format!(
+        "class {} {{\n  final StringsContext _context;\n\n  const {}(this._context);\n",
+        category_id.to_pascal_case(),
+        category_id.to_pascal_case()
+    );
+
+    for (key, attr) in category.ordered_tu_identity_keys() {
+        if base[key].is_term() {
+            continue;
+        }
+
+        let Some(value) = base.get(key).and_then(|x| x.value(attr)) else {
+            continue;
+        };
+
+        let id = dart_string(&message_key(category_id, key, attr));
+        let ident = match attr {
+            Some(attr) => format!("{key}__{attr}").to_lower_camel_case(),
+            None => key.to_lower_camel_case(),
+        };
+        let placeholders = placeholders(value, base);
+
+        out.push('\n');
+        if let Some(description) = category.descriptions.get(key) {
+            for line in description.lines() {
+                out.push_str(&format!("  /// {line}\n"));
+            }
+        }
+
+        if placeholders.is_empty() {
+            out.push_str(&format!(
+                "  String get {ident} => _context.resolve({id});\n"
+            ));
+            continue;
+        }
+
+        let params = placeholders
+            .iter()
+            .map(|x| {
+                format!(
+                    "required {} {}",
+                    x.type_name(),
+                    x.name.to_lower_camel_case()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let args = placeholders
+            .iter()
+            .map(|x| format!("{}: {}", dart_string(&x.name), x.name.to_lower_camel_case()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        out.push_str(&format!(
+            "  String {ident}({{{params}}}) => _context.resolve({id}, {{{args}}});\n"
+        ));
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+fn generate_dart(input: &Project) -> String {
+    let mut out = String::from(concat!(
+        "// Generated by stringly. Do not edit.\n\n",
+        "/// Resolves a message from the ARB files by key, formatting it with the\n",
+        "/// given arguments.\n",
+        "abstract interface class StringsContext {\n",
+        "  String resolve(String id, [Map<String, Object> args = const {}]);\n",
+        "}\n\n",
+        "class Strings {\n",
+    ));
+
+    for (category_id, _) in input.categories.iter() {
+        out.push_str(&format!(
+            "  final {} {};\n",
+            category_id.to_pascal_case(),
+            category_id.to_lower_camel_case()
+        ));
+    }
+    out.push_str("\n  Strings(StringsContext context)");
+    let fields = input
+        .categories
+        .keys()
+        .map(|x| {
+            format!(
+                "{} = {}(context)",
+                x.to_lower_camel_case(),
+                x.to_pascal_case()
+            )
+        })
+        .collect::<Vec<_>>();
+    if !fields.is_empty() {
+        out.push_str(&format!("\n      : {}", fields.join(",\n        ")));
+    }
+    out.push_str(";\n}\n");
+
+    for (category_id, category) in input.categories.iter() {
+        out.push('\n');
+        out.push_str(&generate_dart_class(category_id, category));
+    }
+
+    out
+}
+
+fn l10n_yaml(input: &Project) -> String {
+    let template = input
+        .default_locale
+        .as_ref()
+        .map(|x| x.to_string().replace('-', "_"))
+        .unwrap_or_else(|| "en".to_string());
+
+    format!("arb-dir: .\ntemplate-arb-file: app_{template}.arb\nuse-escaping: true\n")
+}
+
+fn generate_files(input: &Project) -> Result<BTreeMap<String, PathNode>, MessageFormatError> {
+    let mut locales = BTreeMap::new();
+    let mut unsupported = vec![];
+
+    for (category_id, category) in input.categories.iter() {
+        add_category(category_id, category, &mut locales, &mut unsupported);
+    }
+
+    if !unsupported.is_empty() {
+        return Err(MessageFormatError::Unsupported(unsupported));
+    }
+
+    let mut files = locales
+        .into_iter()
+        .map(|(locale, arb)| {
+            (
+                format!("app_{locale}.arb"),
+                PathNode::File(
+                    serde_json::to_string_pretty(&Value::Object(arb))
+                        .unwrap()
+                        .into_bytes(),
+                ),
+            )
+        })
+        .collect::<BTreeMap<_, _>>();
+    files.insert(
+        "l10n.yaml".into(),
+        PathNode::File(l10n_yaml(input).into_bytes()),
+    );
+
+    Ok(files)
+}
+
+pub fn generate(input: Project) -> Result<PathNode, MessageFormatError> {
+    Ok(PathNode::Directory(generate_files(&input)?))
+}
+
+/// Generates the ARB files along with `strings.dart`, a typed wrapper over a
+/// `StringsContext` that the app implements on top of them.
+pub fn generate_with_dart(input: Project) -> Result<PathNode, MessageFormatError> {
+    let mut files = generate_files(&input)?;
+    files.insert(
+        "strings.dart".into(),
+        PathNode::File(generate_dart(&input).into_bytes()),
+    );
+    Ok(PathNode::Directory(files))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flt::test_project;
+
+    #[test]
+    fn arb_files_and_dart() {
+        let project = test_project(&[
+            (
+                "en",
+                concat!(
+                    "# Shown on the list\n",
+                    "files = { $count ->\n",
+                    "    [one] One file in { $folder-name }\n",
+                    "   *[other] { $count } files in { $folder-name }\n",
+                    "}\n",
+                    "login = Log in\n",
+                    "    .title = Don't wait\n",
+                ),
+            ),
+            ("de-AT", "login = Anmelden\n"),
+        ]);
+        let tree = generate_with_dart(project).unwrap();
+
+        assert_eq!(
+            tree.text("app_en.arb"),
+            concat!(
+                "{\n",
+                "  \"@@locale\": \"en\",\n",
+                "  \"@coreFiles\": {\n",
+                "    \"description\": \"Shown on the list\",\n",
+                "    \"placeholders\": {\n",
+                "      \"count\": {\n",
+                "        \"type\": \"num\"\n",
+                "      },\n",
+                "      \"folder_name\": {\n",
+                "        \"type\": \"String\"\n",
+                "      }\n",
+                "    }\n",
+                "  },\n",
+                "  \"coreFiles\": \"{count, plural, one {One file in {folder_name}} ",
+                "other {{count} files in {folder_name}}}\",\n",
+                "  \"coreLogin\": \"Log in\",\n",
+                "  \"coreLoginTitle\": \"Don''t wait\"\n",
+                "}",
+            )
+        );
+        assert_eq!(
+            tree.text("app_de_AT.arb"),
+            "{\n  \"@@locale\": \"de_AT\",\n  \"coreLogin\": \"Anmelden\"\n}"
+        );
+
+        let dart = tree.text("strings.dart");
+        assert!(dart.contains(concat!(
+            "  /// Shown on the list\n",
+            "  String files({required num count, required String folderName}) => ",
+            "_context.resolve('coreFiles', {'count': count, 'folder_name': folderName});\n\n",
+            "  String get login => _context.resolve('coreLogin');\n\n",
+            "  String get loginTitle => _context.resolve('coreLoginTitle');\n",
+        )));
+    }
+}
@@ -0,0 +1,151 @@
+//! Shared logic of the code generation targets, which expose each message
+//! of a category as a typed getter or method.
+
+use crate::{
+    flt::render::{self, VariableKind},
+    ir::{Category, Pattern, Section, TUIdentifier, TranslationUnitMap},
+    printf,
+};
+
+/// A message or attribute to generate an accessor for.
+#[derive(Debug, Clone)]
+pub struct Accessor {
+    pub id: TUIdentifier,
+    pub attr: Option<TUIdentifier>,
+    pub description: Option<String>,
+    /// The heading of the section that starts at this accessor, if any.
+    pub section: Option<String>,
+    /// The arguments of the accessor, as found by [`variables`]. An accessor
+    /// without any is a getter, otherwise a method.
+    pub variables: Vec<(String, VariableKind)>,
+}
+
+impl Accessor {
+    /// The accessor's name before case conversion: `<message>__<attribute>`
+    /// for attributes.
+    pub fn name(&self) -> String {
+        match self.attr.as_ref() {
+            Some(attr) => format!("{}__{}", self.id, attr),
+            None => self.id.to_string(),
+        }
+    }
+
+    /// The base string the accessor reads.
+    pub fn pattern<'a>(&self, category: &'a Category) -> &'a Pattern {
+        let unit = &category.base_strings()[&self.id];
+        unit.value(self.attr.as_ref()).unwrap()
+    }
+}
+
+/// The variables a message or attribute takes, including those only used in
+/// select variants or by messages it references. They are in the argument
+/// order of [`printf::arguments`], which targets backed by format strings
+/// pass them in.
+pub fn variables(
+    base: &TranslationUnitMap,
+    key: &TUIdentifier,
+    attr: Option<&TUIdentifier>,
+) -> Vec<(String, VariableKind)> {
+    let Some(value) = base.get(key).and_then(|x| x.value(attr)) else {
+        return vec![];
+    };
+    let kinds = render::variables(value, base);
+
+    printf::arguments(base, key, attr)
+        .into_iter()
+        .map(|name| {
+            let kind = kinds
+                .iter()
+                .find(|(x, _)| *x == name)
+                .map(|(_, kind)| *kind)
+                .unwrap_or(VariableKind::String);
+            (name, kind)
+        })
+        .collect()
+}
+
+/// The accessors for the messages of a category's base strings, in the
+/// order of [`Category::ordered_keys`]. Terms and messages without a value
+/// are skipped.
+pub fn accessors(category: &Category) -> Vec<Accessor> {
+    let base = category.base_strings();
+    let units = category
+        .ordered_keys(base)
+        .into_iter()
+        .map(|key| (key, &base[key]))
+        .filter(|(_, unit)| {
+            !unit.is_term() && (!unit.main.is_empty() || unit.attributes.is_empty())
+        })
+        .collect::<Vec<_>>();
+
+    let mut out = vec![];
+    let mut current: Option<&Section> = None;
+    for (key, unit) in units {
+        let description = category.descriptions.get(key).cloned();
+
+        // The first accessor of each section carries its heading
+        let section = category.sections.iter().find(|x| x.keys.contains(key));
+        let heading = match section {
+            Some(x) if !current.is_some_and(|current| std::ptr::eq(current, x)) => {
+                Some(x.heading.clone())
+            }
+            _ => None,
+        };
+        current = section;
+
+        out.push(Accessor {
+            id: key.clone(),
+            attr: None,
+            description: description.clone(),
+            section: heading,
+            variables: variables(base, key, None),
+        });
+        out.extend(unit.attributes.keys().map(|attr| Accessor {
+            id: key.clone(),
+            attr: Some(attr.clone()),
+            description: description.clone(),
+            section: None,
+            variables: variables(base, key, Some(attr)),
+        }));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{flt::parse_pattern, ir::TranslationUnit};
+    use icu::locid::locale;
+
+    #[test]
+    fn variables_of_variants_and_references() {
+        let mut base = TranslationUnitMap::new(locale!("en").id);
+        for (id, source) in [
+            ("user", "{ $first } { $last }"),
+            (
+                "added",
+                concat!(
+                    "{ $count ->\n",
+                    "    [one] { user } added a file\n",
+                    "   *[other] { NUMBER($count) } files\n",
+                    "}",
+                ),
+            ),
+        ] {
+            let key = TUIdentifier::try_from(id).unwrap();
+            let mut unit = TranslationUnit::new(key.clone());
+            unit.main = parse_pattern(source).unwrap();
+            base.insert(key, unit);
+        }
+
+        let key = TUIdentifier::try_from("added").unwrap();
+        assert_eq!(
+            variables(&base, &key, None),
+            vec![
+                ("count".to_string(), VariableKind::Number),
+                ("first".to_string(), VariableKind::String),
+                ("last".to_string(), VariableKind::String),
+            ]
+        );
+    }
+}
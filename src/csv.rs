@@ -0,0 +1,135 @@
+//! CSV and TSV files in the same layout as the sheets of [`crate::xlsx`], one
+//! file per category, with a `manifest.toml` naming the project and listing
+//! each category's file.
+
+use std::{collections::BTreeMap, path::Path};
+
+use icu::locid::LanguageIdentifier;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ir::{Keys, Project},
+    xlsx, PathNode,
+};
+
+const MANIFEST: &str = "manifest.toml";
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Manifest {
+    name: String,
+    default_locale: Option<LanguageIdentifier>,
+    #[serde(flatten)]
+    categories: BTreeMap<String, ManifestCategory>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct ManifestCategory {
+    name: String,
+    file: String,
+}
+
+fn delimiter(path: &Path) -> u8 {
+    match path.extension().and_then(|x| x.to_str()) {
+        Some("tsv") => b'\t',
+        _ => b',',
+    }
+}
+
+fn generate_files(
+    project: Project,
+    extension: &str,
+    delimiter: u8,
+) -> Result<PathNode, ::csv::Error> {
+    let mut files = BTreeMap::new();
+    let mut manifest = Manifest {
+        name: project.name,
+        default_locale: project.default_locale,
+        categories: Default::default(),
+    };
+
+    for (category_id, category) in project.categories.iter() {
+        let rows = xlsx::sheet_rows(category);
+        let width = rows[0].len();
+
+        let mut writer = ::csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .from_writer(vec![]);
+        for row in rows.iter() {
+            writer.write_record(
+                (0..width).map(|i| row.get(i).cloned().flatten().unwrap_or_default()),
+            )?;
+        }
+
+        let file = format!("{category_id}.{extension}");
+        files.insert(
+            file.clone(),
+            PathNode::File(writer.into_inner().map_err(|x| x.into_error())?),
+        );
+        manifest.categories.insert(
+            category_id.to_string(),
+            ManifestCategory {
+                name: category.name.clone(),
+                file,
+            },
+        );
+    }
+
+    files.insert(
+        MANIFEST.into(),
+        PathNode::File(toml::to_string(&manifest).unwrap().into_bytes()),
+    );
+
+    Ok(PathNode::Directory(files))
+}
+
+pub fn generate(project: Project) -> Result<PathNode, ::csv::Error> {
+    generate_files(project, "csv", b',')
+}
+
+pub fn generate_tsv(project: Project) -> Result<PathNode, ::csv::Error> {
+    generate_files(project, "tsv", b'\t')
+}
+
+/// Reads the cells of a CSV or TSV file, with empty cells as `None`.
+pub fn read_rows(path: &Path) -> anyhow::Result<Vec<Vec<Option<String>>>> {
+    let mut reader = ::csv::ReaderBuilder::new()
+        .delimiter(delimiter(path))
+        .has_headers(false)
+        .flexible(true)
+        .from_path(path)?;
+
+    let mut rows = vec![];
+    for record in reader.records() {
+        rows.push(
+            record?
+                .iter()
+                .map(|x| Some(x.to_string()).filter(|x| !x.is_empty()))
+                .collect(),
+        );
+    }
+    Ok(rows)
+}
+
+/// Loads the categories listed in a directory's `manifest.toml`.
+pub fn load_project_from_path(path: &Path, keys: &mut Keys) -> anyhow::Result<Project> {
+    let manifest = std::fs::read_to_string(path.join(MANIFEST))?;
+    let manifest: Manifest = toml::from_str(&manifest)?;
+
+    let mut project = Project {
+        name: manifest.name,
+        default_locale: manifest.default_locale,
+        categories: Default::default(),
+    };
+
+    for (category_id, entry) in manifest.categories.into_iter() {
+        let rows = read_rows(&path.join(&entry.file))?;
+        let category_id = keys.category(&category_id)?;
+        if let Some(category) = xlsx::parse_sheet(&entry.name, category_id, rows, keys)? {
+            project.categories.insert(category);
+        }
+    }
+
+    Ok(project)
+}
@@ -5,11 +5,15 @@ use icu::locid::{locale, LanguageIdentifier};
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    ir::{CIdentifier, Category, Project, TUIdentifier, TranslationUnit, TranslationUnitMap},
+    ir::{
+        pattern::is_fluent_syntax, Category, Keys, Metadata, Pattern, Project, Section,
+        TUIdentifier, TranslationUnit, TranslationUnitMap, UnitKind,
+    },
     PathNode,
 };
 
-mod serializer;
+pub mod render;
+pub(crate) mod serializer;
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -24,6 +28,13 @@ fn default_locale() -> LanguageIdentifier {
     locale!("en").id
 }
 
+/// The sidecar holding the workflow metadata of every unit, next to
+/// `stringly.toml`.
+const METADATA_FILE: &str = "stringly.meta.toml";
+
+/// Metadata by category, locale and unit key.
+type MetadataFile = BTreeMap<String, BTreeMap<String, BTreeMap<String, Metadata>>>;
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 struct CategoryConfig {
@@ -37,6 +48,59 @@ pub fn parse_flt(path: &Path) -> fluent_syntax::parser::Result<String> {
     fluent_syntax::parser::parse(flt_str)
 }
 
+/// Parses the Fluent source of a pattern, as written by [`pattern_source`].
+pub fn parse_pattern(value: &str) -> Result<Pattern, ParserError> {
+    if value.trim().is_empty() {
+        return Ok(Pattern::default());
+    }
+
+    let resource = fluent_syntax::parser::parse(format!(
+        "pattern = {}\n",
+        value.trim().replace('\n', "\n    ")
+    ))
+    .map_err(|(_, mut errors)| errors.remove(0))?;
+
+    match resource.body.into_iter().next() {
+        Some(ast::Entry::Message(ast::Message {
+            value: Some(value), ..
+        })) => Ok(Pattern::from(&value)),
+        _ => Ok(Pattern::default()),
+    }
+}
+
+/// Parses the Fluent source of a pattern, falling back to taking the source
+/// as plain text if it is not valid, for hand-edited files such as
+/// spreadsheets.
+pub fn parse_pattern_lossy(value: &str) -> Pattern {
+    parse_pattern(value).unwrap_or_else(|_| Pattern::text(value))
+}
+
+/// Serializes a pattern as source text without the leading space and
+/// indentation it would have as the value of a message.
+pub fn pattern_source(pattern: &Pattern) -> String {
+    let source = serializer::serialize_pattern(&pattern.to_fluent());
+    let source = source
+        .strip_prefix('\n')
+        .or_else(|| source.strip_prefix(' '))
+        .unwrap_or(&source);
+    source.trim_start_matches("    ").replace("\n    ", "\n")
+}
+
+/// Escapes plain text so that it is stored as a pattern verbatim.
+pub fn escape_text(value: &str) -> String {
+    let mut line_start = true;
+    value
+        .chars()
+        .map(|c| {
+            if is_fluent_syntax(c, &mut line_start) {
+                format!("{{\"{c}\"}}")
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
+
 pub fn generate(input: Project) -> Result<PathNode, ParserError> {
     let mut files = BTreeMap::new();
 
@@ -45,34 +109,44 @@ pub fn generate(input: Project) -> Result<PathNode, ParserError> {
         default_locale: input.default_locale,
         categories: Default::default(),
     };
+    let mut metadata = MetadataFile::new();
 
     for (k, v) in input.categories.into_iter() {
         config.categories.insert(
             k.to_string(),
             CategoryConfig {
-                name: v.name,
-                default_locale: v.default_locale,
+                name: v.name.clone(),
+                default_locale: v.default_locale.clone(),
             },
         );
         let mut subfiles = BTreeMap::new();
         for m in v.translation_units.values() {
             let lang = m.locale.clone();
-            let x = match m.to_flt_resource(&v.descriptions) {
-                Ok(x) => x,
-                Err(e) => {
-                    eprintln!("Error parsing translation unit: {} {}", k, m.locale);
-                    eprintln!("{:?}", e);
-                    std::process::exit(1);
-                }
-            };
+            let x = m.to_flt_resource(&v);
             subfiles.insert(
                 format!("{lang}.flt"),
                 PathNode::File(fluent_syntax::serializer::serialize(&x).into_bytes()),
             );
+
+            for (key, unit) in m.iter().filter(|(_, x)| !x.metadata.is_empty()) {
+                metadata
+                    .entry(k.to_string())
+                    .or_default()
+                    .entry(lang.to_string())
+                    .or_default()
+                    .insert(key.to_string(), unit.metadata.clone());
+            }
         }
         files.insert(k.to_string(), PathNode::Directory(subfiles));
     }
 
+    if !metadata.is_empty() {
+        files.insert(
+            METADATA_FILE.into(),
+            PathNode::File(toml::to_string(&metadata).unwrap().into_bytes()),
+        );
+    }
+
     files.insert(
         "stringly.toml".into(),
         PathNode::File(toml::to_string(&config).unwrap().into_bytes()),
@@ -81,7 +155,7 @@ pub fn generate(input: Project) -> Result<PathNode, ParserError> {
     Ok(PathNode::Directory(files))
 }
 
-pub fn load_project_from_path(path: &Path) -> anyhow::Result<Project> {
+pub fn load_project_from_path(path: &Path, keys: &mut Keys) -> anyhow::Result<Project> {
     let config = std::fs::read_to_string(path.join("stringly.toml"))?;
     let config: ProjectConfig = toml::from_str(&config)?;
 
@@ -91,13 +165,22 @@ pub fn load_project_from_path(path: &Path) -> anyhow::Result<Project> {
         categories: Default::default(),
     };
 
+    let mut metadata: MetadataFile = match std::fs::read_to_string(path.join(METADATA_FILE)) {
+        Ok(x) => toml::from_str(&x)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Default::default(),
+        Err(e) => return Err(e.into()),
+    };
+
     for (category_id, category) in config.categories.into_iter() {
         let dir = path.join(&category_id).read_dir()?;
-        let category_id = CIdentifier::try_from(category_id).unwrap();
+        let category_metadata = metadata.remove(&category_id).unwrap_or_default();
+        let category_id = keys.category(&category_id)?;
 
         let mut category = Category {
             key: category_id.clone(),
             descriptions: Default::default(),
+            notes: None,
+            sections: Default::default(),
             name: category.name,
             default_locale: category.default_locale.clone(),
             translation_units: Default::default(),
@@ -117,9 +200,14 @@ pub fn load_project_from_path(path: &Path) -> anyhow::Result<Project> {
             let locale_str = flt_path.file_stem().and_then(|x| x.to_str()).unwrap();
             let locale = LanguageIdentifier::from_str(locale_str).unwrap();
             let flt: ast::Resource<String> = parse_flt(&flt_path).unwrap();
-            category
-                .translation_units
-                .insert(TranslationUnitMap::from_flt_resource(locale, &flt));
+            if locale == category.default_locale {
+                load_comments(&mut category, &flt);
+            }
+            let mut units = TranslationUnitMap::from_flt_resource(locale, &flt);
+            if let Some(x) = category_metadata.get(locale_str) {
+                load_metadata(&mut units, x);
+            }
+            category.translation_units.insert(units);
         }
 
         project.categories.insert(category);
@@ -128,6 +216,67 @@ pub fn load_project_from_path(path: &Path) -> anyhow::Result<Project> {
     Ok(project)
 }
 
+fn load_metadata(units: &mut TranslationUnitMap, metadata: &BTreeMap<String, Metadata>) {
+    for (key, metadata) in metadata.iter() {
+        let unit = TUIdentifier::try_from(key)
+            .ok()
+            .and_then(|x| units.get_mut(&x));
+        match unit {
+            Some(unit) => unit.metadata = metadata.clone(),
+            None => eprintln!("[{}] No unit {} for metadata; skipping", units.locale, key),
+        }
+    }
+}
+
+fn comment_text(comment: &ast::Comment<String>) -> String {
+    comment.content.join("\n")
+}
+
+/// Reads the comments of the base strings into the category: message
+/// comments become descriptions, group comments become sections and resource
+/// comments become the category's notes.
+fn load_comments(category: &mut Category, resource: &ast::Resource<String>) {
+    let mut notes = vec![];
+    let mut in_section = false;
+
+    for entry in resource.body.iter() {
+        let (key, comment) = match entry {
+            ast::Entry::ResourceComment(x) => {
+                notes.push(comment_text(x));
+                continue;
+            }
+            // An empty group comment closes the current section
+            ast::Entry::GroupComment(x) => {
+                let heading = comment_text(x);
+                in_section = !heading.trim().is_empty();
+                if in_section {
+                    category.sections.push(Section {
+                        heading,
+                        keys: vec![],
+                    });
+                }
+                continue;
+            }
+            ast::Entry::Message(x) => (TUIdentifier::from(x), x.comment.as_ref()),
+            ast::Entry::Term(x) => (TUIdentifier::from(x), x.comment.as_ref()),
+            _ => continue,
+        };
+
+        if let Some(comment) = comment {
+            category
+                .descriptions
+                .insert(key.clone(), comment_text(comment));
+        }
+        if in_section {
+            category.sections.last_mut().unwrap().keys.push(key);
+        }
+    }
+
+    if !notes.is_empty() {
+        category.notes = Some(notes.join("\n\n"));
+    }
+}
+
 impl TranslationUnitMap {
     pub fn from_flt_resource(
         default_locale: LanguageIdentifier,
@@ -136,142 +285,207 @@ impl TranslationUnitMap {
         let mut tm = TranslationUnitMap::new(default_locale);
 
         for resource in value.body.iter() {
-            match resource {
-                ast::Entry::Message(x) => {
-                    let tu_id = TUIdentifier::from(x);
-                    let main = serializer::serialize_pattern(x.value.as_ref().unwrap());
-                    let attributes = x
-                        .attributes
-                        .iter()
-                        .map(|x| {
-                            (
-                                TUIdentifier::from(x),
-                                serializer::serialize_pattern(&x.value),
-                            )
-                        })
-                        .collect();
-                    tm.translation_units.insert(TranslationUnit {
-                        key: tu_id,
-                        main,
-                        attributes,
-                    });
-                }
-                ast::Entry::Term(x) => {
-                    let tu_id = TUIdentifier::from(x);
-                    let main = serializer::serialize_pattern(&x.value);
-                    let attributes = x
-                        .attributes
-                        .iter()
-                        .map(|x| {
-                            (
-                                TUIdentifier::from(x),
-                                serializer::serialize_pattern(&x.value),
-                            )
-                        })
-                        .collect();
-
-                    tm.translation_units.insert(TranslationUnit {
-                        key: tu_id,
-                        main,
-                        attributes,
-                    });
-                }
-                _ => {}
-            }
+            let (tu_id, kind, value, attributes) = match resource {
+                ast::Entry::Message(x) => (
+                    TUIdentifier::from(x),
+                    UnitKind::Message,
+                    x.value.as_ref(),
+                    &x.attributes,
+                ),
+                ast::Entry::Term(x) => (
+                    TUIdentifier::from(x),
+                    UnitKind::Term,
+                    Some(&x.value),
+                    &x.attributes,
+                ),
+                _ => continue,
+            };
+
+            tm.translation_units.insert(TranslationUnit {
+                key: tu_id,
+                kind,
+                main: value.map(Pattern::from).unwrap_or_default(),
+                attributes: attributes
+                    .iter()
+                    .map(|x| (TUIdentifier::from(x), Pattern::from(&x.value)))
+                    .collect(),
+                metadata: Default::default(),
+            });
         }
 
         tm
     }
 
-    pub fn to_flt_resource(
-        &self,
-        descriptions: &BTreeMap<TUIdentifier, String>,
-    ) -> Result<ast::Resource<String>, ParserError> {
-        let resources =
-            self.translation_units
+    /// The units as a Fluent resource, with the category's notes, sections
+    /// and descriptions as comments.
+    pub fn to_flt_resource(&self, category: &Category) -> ast::Resource<String> {
+        let comment = |value: &str| ast::Comment {
+            content: value.lines().map(str::to_string).collect(),
+        };
+        let mut body = vec![];
+
+        if let Some(notes) = category.notes.as_ref() {
+            body.push(ast::Entry::ResourceComment(comment(notes)));
+        }
+
+        for key in category.ordered_keys(self) {
+            let value = &self[key];
+
+            if let Some(section) = category.section_starting_at(self, key) {
+                body.push(ast::Entry::GroupComment(comment(&section.heading)));
+            }
+
+            let comment = category.descriptions.get(key).map(|x| comment(x));
+
+            let attributes = value
+                .attributes
                 .iter()
-                .fold(String::new(), |mut input, (key, value)| {
-                    // eprintln!("{} [{:?}]", key, value);
-                    let comment = if let Some(value) = descriptions.get(key) {
-                        Some(ast::Comment {
-                            content: vec![multiline_main(&value)],
-                        })
-                    } else {
+                .map(|(k, v)| ast::Attribute {
+                    id: ast::Identifier {
+                        name: k.deref().to_string(),
+                    },
+                    value: v.to_fluent(),
+                })
+                .collect();
+
+            body.push(match value.kind {
+                UnitKind::Term => ast::Entry::Term(ast::Term {
+                    id: ast::Identifier {
+                        name: key.trim_start_matches('-').to_string(),
+                    },
+                    value: value.main.to_fluent(),
+                    attributes,
+                    comment,
+                }),
+                UnitKind::Message => ast::Entry::Message(ast::Message {
+                    id: ast::Identifier {
+                        name: key.deref().to_string(),
+                    },
+                    // Fluent messages need a value unless they have attributes
+                    value: if value.main.is_empty() && !value.attributes.is_empty() {
                         None
-                    };
-
-                    let message = ast::Message {
-                        id: ast::Identifier {
-                            name: key.deref().to_string(),
-                        },
-                        value: Some(ast::Pattern {
-                            elements: vec![ast::PatternElement::TextElement {
-                                value: multiline_main(&value.main),
-                            }],
-                        }),
-                        attributes: value
-                            .attributes
-                            .iter()
-                            .map(|(k, v)| ast::Attribute {
-                                id: ast::Identifier {
-                                    name: k.deref().to_string(),
-                                },
-                                value: ast::Pattern {
-                                    elements: vec![ast::PatternElement::TextElement {
-                                        value: multiline_attr(&v),
-                                    }],
-                                },
-                            })
-                            .collect::<Vec<_>>(),
-                        comment,
-                    };
-
-                    input.push_str(&serializer::serialize_message(&message));
-
-                    input
-                });
-
-        // eprintln!("[{}]", resources);
-
-        fluent_syntax::parser::parse(resources.clone()).map_err(|(_, mut errors)| {
-            let error = errors.remove(0);
-
-            // let chonk = resources
-            //     .chars()
-            //     .skip(error.pos.start - 10)
-            //     .take(20)
-            //     .collect::<String>();
-            // eprintln!("Erro here: [{chonk}]",);
-            error
-        })
+                    } else {
+                        Some(value.main.to_fluent())
+                    },
+                    attributes,
+                    comment,
+                }),
+            });
+        }
+
+        ast::Resource { body }
     }
 }
 
-fn multiline_main(value: &str) -> String {
-    format!(
-        "{}\n",
-        escape(value.trim())
-            .split("\n")
-            .collect::<Vec<_>>()
-            .join("\n    ")
-    )
-}
+/// A project with a single `core` category, read from the Fluent source of
+/// each locale. The first locale is the category's default.
+#[cfg(test)]
+pub(crate) fn test_project(sources: &[(&str, &str)]) -> Project {
+    let default_locale = LanguageIdentifier::from_str(sources[0].0).unwrap();
+    let mut category = Category {
+        key: crate::ir::CIdentifier::try_from("core").unwrap(),
+        name: "Core".to_string(),
+        default_locale: default_locale.clone(),
+        descriptions: Default::default(),
+        notes: None,
+        sections: Default::default(),
+        translation_units: Default::default(),
+    };
 
-fn multiline_attr(value: &str) -> String {
-    format!(
-        "{}\n",
-        escape(value.trim())
-            .split("\n")
-            .collect::<Vec<_>>()
-            .join("\n        ")
-    )
+    for (locale, source) in sources {
+        let locale = LanguageIdentifier::from_str(locale).unwrap();
+        let resource = fluent_syntax::parser::parse(source.to_string()).unwrap();
+        if locale == default_locale {
+            load_comments(&mut category, &resource);
+        }
+        category
+            .translation_units
+            .insert(TranslationUnitMap::from_flt_resource(locale, &resource));
+    }
+
+    let mut project = Project {
+        name: "Test".to_string(),
+        default_locale: Some(default_locale),
+        categories: Default::default(),
+    };
+    project.categories.insert(category);
+    project
 }
 
-fn escape(value: &str) -> String {
-    value
-        .replace("*", "{\"*\"}")
-        .replace("\\(", "{\"(\"}")
-        .replace("\\)", "{\")\"}")
-        .replace("\\{", "{\"{\"}")
-        .replace("\\}", "{\"}\"}")
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::CIdentifier;
+
+    fn category() -> Category {
+        Category {
+            key: CIdentifier::try_from("core").unwrap(),
+            name: "Core".to_string(),
+            default_locale: locale!("en").id,
+            descriptions: Default::default(),
+            notes: None,
+            sections: Default::default(),
+            translation_units: Default::default(),
+        }
+    }
+
+    #[test]
+    fn terms_round_trip() {
+        let resource = fluent_syntax::parser::parse(
+            "-brand-name = Acme\nwelcome = Welcome to { -brand-name }!\n".to_string(),
+        )
+        .unwrap();
+        let units = TranslationUnitMap::from_flt_resource(locale!("en").id, &resource);
+        let category = category();
+
+        let term = &units[&TUIdentifier::try_from("-brand-name").unwrap()];
+        assert!(term.is_term());
+        assert!(!units[&TUIdentifier::try_from("welcome").unwrap()].is_term());
+
+        let resource = units.to_flt_resource(&category);
+        assert!(matches!(
+            &resource.body[0],
+            ast::Entry::Term(x) if x.id.name == "brand-name"
+        ));
+        assert!(matches!(&resource.body[1], ast::Entry::Message(_)));
+    }
+
+    #[test]
+    fn escaped_text_round_trips() {
+        let text = "[draft] {name}\n*.ftl";
+        assert_eq!(
+            parse_pattern(&escape_text(text)).unwrap(),
+            Pattern::text(text)
+        );
+        assert_eq!(
+            parse_pattern(&pattern_source(&Pattern::text(text))).unwrap(),
+            Pattern::text(text)
+        );
+    }
+
+    #[test]
+    fn comments_round_trip() {
+        let source = concat!(
+            "### Shown on the home screen\n",
+            "\n",
+            "# Greets the user\n",
+            "welcome = Welcome!\n",
+            "\n",
+            "## Sidebar\n",
+            "\n",
+            "files = Files\n",
+            "folders = Folders\n",
+        );
+        let resource = fluent_syntax::parser::parse(source.to_string()).unwrap();
+        let units = TranslationUnitMap::from_flt_resource(locale!("en").id, &resource);
+        let mut category = category();
+        load_comments(&mut category, &resource);
+
+        assert_eq!(category.notes.as_deref(), Some("Shown on the home screen"));
+        assert_eq!(category.sections[0].heading, "Sidebar");
+        assert_eq!(category.sections[0].keys.len(), 2);
+
+        let resource = units.to_flt_resource(&category);
+        assert_eq!(fluent_syntax::serializer::serialize(&resource), source);
+    }
 }
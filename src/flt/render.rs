@@ -0,0 +1,227 @@
+//! Flattening of patterns for targets whose strings only support plain text
+//! interleaved with argument substitutions.
+//!
+//! Message and term references are inlined from the surrounding
+//! [`TranslationUnitMap`], and select expressions collapse to their default
+//! variant; targets that can express plurals should split those out with
+//! [`crate::plural::PluralSelect`] before rendering.
+
+use crate::{
+    ir::{Element, Expression, Pattern, Select, TUIdentifier, TranslationUnitMap},
+    plural,
+};
+
+/// Guards against cycles between messages referencing each other.
+const MAX_DEPTH: usize = 8;
+
+/// How a variable is used within a pattern, for targets that need to pick a
+/// format specifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariableKind {
+    String,
+    Number,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Text(String),
+    Variable(String, VariableKind),
+}
+
+/// Renders a pattern into text and variable segments. Adjacent text is merged.
+pub fn segments(pattern: &Pattern, units: &TranslationUnitMap) -> Vec<Segment> {
+    let mut out = vec![];
+    render_pattern(pattern, units, 0, &mut out);
+    out
+}
+
+/// Every variable used anywhere in a pattern, including all select variants
+/// and referenced messages, in order of first appearance.
+pub fn variables(pattern: &Pattern, units: &TranslationUnitMap) -> Vec<(String, VariableKind)> {
+    let mut out: Vec<(String, VariableKind)> = vec![];
+    collect_pattern(pattern, units, 0, &mut out);
+    out
+}
+
+/// The index of a variable in an argument list, appending it if it isn't
+/// there yet.
+pub fn argument_index(args: &mut Vec<String>, name: &str) -> usize {
+    match args.iter().position(|x| x == name) {
+        Some(i) => i,
+        None => {
+            args.push(name.to_string());
+            args.len() - 1
+        }
+    }
+}
+
+fn push_text(out: &mut Vec<Segment>, value: &str) {
+    if let Some(Segment::Text(text)) = out.last_mut() {
+        text.push_str(value);
+    } else {
+        out.push(Segment::Text(value.to_string()));
+    }
+}
+
+fn render_pattern(
+    pattern: &Pattern,
+    units: &TranslationUnitMap,
+    depth: usize,
+    out: &mut Vec<Segment>,
+) {
+    for element in pattern.elements.iter() {
+        match element {
+            Element::Text(value) => push_text(out, value),
+            Element::Placeable(expression) => render_expression(expression, units, depth, out),
+            Element::Select(select) => {
+                if let Some(variant) = select.default_variant() {
+                    render_pattern(&variant.value, units, depth, out);
+                }
+            }
+        }
+    }
+}
+
+fn render_expression(
+    expression: &Expression,
+    units: &TranslationUnitMap,
+    depth: usize,
+    out: &mut Vec<Segment>,
+) {
+    match expression {
+        Expression::String(value) | Expression::Number(value) => push_text(out, value),
+        Expression::Variable(name) => {
+            out.push(Segment::Variable(name.clone(), VariableKind::String))
+        }
+        Expression::Function { name, arguments } => match arguments.positional.first() {
+            Some(Expression::Variable(var)) => {
+                let kind = if name == "NUMBER" {
+                    VariableKind::Number
+                } else {
+                    VariableKind::String
+                };
+                out.push(Segment::Variable(var.clone(), kind));
+            }
+            Some(x) => render_expression(x, units, depth, out),
+            None => {}
+        },
+        Expression::Message { .. } | Expression::Term { .. } => {
+            let (id, attribute) = expression.reference().unwrap();
+            match resolve_reference(&id, attribute, units, depth) {
+                Some(pattern) => render_pattern(pattern, units, depth + 1, out),
+                None => {
+                    eprintln!("[{}] Could not resolve reference: {}", units.locale, id);
+                    push_text(out, &id);
+                }
+            }
+        }
+    }
+}
+
+fn resolve_reference<'a>(
+    id: &str,
+    attribute: Option<&str>,
+    units: &'a TranslationUnitMap,
+    depth: usize,
+) -> Option<&'a Pattern> {
+    if depth >= MAX_DEPTH {
+        return None;
+    }
+
+    let unit = units.get(&TUIdentifier::try_from(id).ok()?)?;
+    match attribute {
+        Some(attr) => unit.attributes.get(&TUIdentifier::try_from(attr).ok()?),
+        None => Some(&unit.main),
+    }
+}
+
+fn collect_pattern(
+    pattern: &Pattern,
+    units: &TranslationUnitMap,
+    depth: usize,
+    out: &mut Vec<(String, VariableKind)>,
+) {
+    for element in pattern.elements.iter() {
+        match element {
+            Element::Text(_) => {}
+            Element::Placeable(expression) => collect_expression(expression, units, depth, out),
+            Element::Select(select) => collect_select(select, units, depth, out),
+        }
+    }
+}
+
+fn collect_select(
+    select: &Select,
+    units: &TranslationUnitMap,
+    depth: usize,
+    out: &mut Vec<(String, VariableKind)>,
+) {
+    collect_expression(&select.selector, units, depth, out);
+
+    // Selecting on plural categories implies a number
+    let is_plural = plural::is_plural_select(select);
+    if let (true, Expression::Variable(name)) = (is_plural, &select.selector) {
+        if let Some(existing) = out.iter_mut().find(|(x, _)| x == name) {
+            existing.1 = VariableKind::Number;
+        }
+    }
+
+    for variant in select.variants.iter() {
+        collect_pattern(&variant.value, units, depth, out);
+    }
+}
+
+fn collect_expression(
+    expression: &Expression,
+    units: &TranslationUnitMap,
+    depth: usize,
+    out: &mut Vec<(String, VariableKind)>,
+) {
+    if let Some((id, attribute)) = expression.reference() {
+        if let Some(pattern) = resolve_reference(&id, attribute, units, depth) {
+            collect_pattern(pattern, units, depth + 1, out);
+        }
+        return;
+    }
+
+    let mut segments = vec![];
+    render_expression(expression, units, depth, &mut segments);
+
+    for segment in segments {
+        if let Segment::Variable(name, kind) = segment {
+            match out.iter_mut().find(|(x, _)| *x == name) {
+                Some(existing) if kind == VariableKind::Number => existing.1 = kind,
+                Some(_) => {}
+                None => out.push((name, kind)),
+            }
+        }
+    }
+}
+
+/// Resolves the escape sequences Fluent allows in string literals.
+pub fn unescape_literal(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some(x @ ('u' | 'U')) => {
+                let len = if x == 'u' { 4 } else { 6 };
+                let hex = chars.by_ref().take(len).collect::<String>();
+                match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    Some(c) => out.push(c),
+                    None => out.push(char::REPLACEMENT_CHARACTER),
+                }
+            }
+            Some(x) => out.push(x),
+            None => out.push('\\'),
+        }
+    }
+
+    out
+}
@@ -76,6 +76,12 @@ pub fn serialize_term<'s, S: Slice<'s>>(pattern: &Term<S>) -> String {
     ser.into_serialized_text()
 }
 
+pub fn serialize_inline_expression<'s, S: Slice<'s>>(expr: &InlineExpression<S>) -> String {
+    let mut ser = Serializer::new(Default::default());
+    ser.serialize_inline_expression(expr);
+    ser.into_serialized_text()
+}
+
 /// Serializes an abstract syntax tree representing a Fluent Translation List into a
 /// String accepting custom options.
 pub fn serialize_with_options<'s, S: Slice<'s>>(
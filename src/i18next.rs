@@ -0,0 +1,202 @@
+//! i18next JSON resources, one `<locale>/<category>.json` per category.
+//!
+//! Messages with attributes become nested objects holding the attributes,
+//! with the message value itself under `value`. Plural selects are split into
+//! `<key>_<category>` keys, selecting on i18next's `count` option. Other
+//! selects are reduced to their default variant.
+
+use std::collections::BTreeMap;
+
+use fluent_syntax::parser::ParserError;
+use serde_json::{Map, Value};
+
+use crate::{
+    flt::render,
+    ir::{Category, Element, Pattern, Project, TranslationUnitMap},
+    plural::{self, PluralSelect},
+    PathNode,
+};
+
+/// The key holding a message's own value when it also has attributes.
+const VALUE_KEY: &str = "value";
+
+/// i18next always picks plural forms by the `count` option.
+const COUNT_VARIABLE: &str = "count";
+
+fn interpolate(segments: &[render::Segment], count: Option<&str>) -> String {
+    segments
+        .iter()
+        .map(|x| match x {
+            render::Segment::Text(text) => text.clone(),
+            render::Segment::Variable(name, _) if Some(name.as_str()) == count => {
+                format!("{{{{{COUNT_VARIABLE}}}}}")
+            }
+            render::Segment::Variable(name, _) => format!("{{{{{name}}}}}"),
+        })
+        .collect()
+}
+
+/// Whether text would be read as interpolation or nesting, which i18next has
+/// no way to escape.
+fn is_reserved(text: &str) -> bool {
+    text.contains("{{") || text.contains("$t(")
+}
+
+/// Inserts the entries for one value into `out`, splitting plural selects
+/// into suffixed keys. `name` identifies the value in warnings.
+fn insert_value(
+    out: &mut Map<String, Value>,
+    name: &str,
+    key: &str,
+    pattern: &Pattern,
+    units: &TranslationUnitMap,
+) {
+    let plural = PluralSelect::from_pattern(pattern);
+    let patterns = match plural {
+        Some(select) => plural::cardinal_categories(&units.locale)
+            .into_iter()
+            .map(|category| (format!("{key}_{category}"), select.expand(category)))
+            .collect(),
+        None => vec![(key.to_string(), pattern.clone())],
+    };
+
+    if patterns.iter().any(|(_, pattern)| {
+        pattern
+            .elements
+            .iter()
+            .any(|x| matches!(x, Element::Select(_)))
+    }) {
+        eprintln!(
+            "[{}] {} has a select i18next cannot express; using the default variant",
+            units.locale, name
+        );
+    }
+
+    let mut reserved = false;
+    for (key, pattern) in patterns {
+        let segments = render::segments(&pattern, units);
+        reserved |= segments
+            .iter()
+            .any(|x| matches!(x, render::Segment::Text(text) if is_reserved(text)));
+        out.insert(
+            key,
+            Value::String(interpolate(&segments, plural.map(|x| x.variable))),
+        );
+    }
+
+    if reserved {
+        eprintln!(
+            "[{}] {} has text i18next reads as interpolation",
+            units.locale, name
+        );
+    }
+}
+
+fn generate_resource(category: &Category, units: &TranslationUnitMap) -> Map<String, Value> {
+    let mut out = Map::new();
+
+    for (key, unit) in units.iter() {
+        // Terms only exist to be inlined into messages
+        if unit.is_term() {
+            continue;
+        }
+
+        if category.base_strings().get(key).is_none() {
+            eprintln!(
+                "[{}] {} is not in the base strings; skipping",
+                units.locale, key
+            );
+            continue;
+        }
+
+        if unit.attributes.is_empty() {
+            insert_value(&mut out, key, key, &unit.main, units);
+            continue;
+        }
+
+        let mut nested = Map::new();
+        if !unit.main.is_empty() {
+            insert_value(&mut nested, key, VALUE_KEY, &unit.main, units);
+        }
+        for (attr, value) in unit.attributes.iter() {
+            if &**attr == VALUE_KEY && !unit.main.is_empty() {
+                eprintln!(
+                    "[{}] {}.{} clashes with the message value; skipping",
+                    units.locale, key, attr
+                );
+                continue;
+            }
+            insert_value(&mut nested, &format!("{key}.{attr}"), attr, value, units);
+        }
+        out.insert(key.to_string(), Value::Object(nested));
+    }
+
+    out
+}
+
+pub fn generate(input: Project) -> Result<PathNode, ParserError> {
+    let mut locales: BTreeMap<String, BTreeMap<String, PathNode>> = BTreeMap::new();
+
+    for (category_id, category) in input.categories.iter() {
+        for (locale, units) in category.translation_units.iter() {
+            let resource = generate_resource(category, units);
+            locales.entry(locale.to_string()).or_default().insert(
+                format!("{category_id}.json"),
+                PathNode::File(
+                    serde_json::to_string_pretty(&Value::Object(resource))
+                        .unwrap()
+                        .into_bytes(),
+                ),
+            );
+        }
+    }
+
+    Ok(PathNode::Directory(
+        locales
+            .into_iter()
+            .map(|(k, v)| (k, PathNode::Directory(v)))
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flt::test_project;
+
+    #[test]
+    fn plurals_and_attributes() {
+        let project = test_project(&[(
+            "en",
+            concat!(
+                "-brand = Stringly\n",
+                "files = { $num ->\n",
+                "    [one] One file in { -brand }\n",
+                "   *[other] { $num } files in { $folder }\n",
+                "}\n",
+                "login = Log in\n",
+                "    .title = Log in as { $user }\n",
+                "owner = { $gender ->\n",
+                "    [male] his\n",
+                "   *[other] their\n",
+                "} files\n",
+            ),
+        )]);
+        let tree = generate(project).unwrap();
+
+        assert_eq!(
+            tree.text("en/core.json"),
+            concat!(
+                "{\n",
+                "  \"files_one\": \"One file in Stringly\",\n",
+                "  \"files_other\": \"{{count}} files in {{folder}}\",\n",
+                "  \"login\": {\n",
+                "    \"title\": \"Log in as {{user}}\",\n",
+                "    \"value\": \"Log in\"\n",
+                "  },\n",
+                "  \"owner\": \"their files\"\n",
+                "}",
+            )
+        );
+    }
+}
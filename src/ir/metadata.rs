@@ -0,0 +1,152 @@
+//! Workflow metadata of a translation unit: where its value came from, who
+//! touched it last and which version of the base string it translates.
+//!
+//! The source hash is a 64-bit FNV-1a hash of the base string's Fluent
+//! source, so that it stays the same across builds and platforms. A
+//! translation whose hash no longer matches its base string is out of date.
+
+use std::{fmt::Display, str::FromStr, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use super::TranslationUnit;
+use crate::flt::pattern_source;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Status {
+    Untranslated,
+    MachineTranslated,
+    NeedsReview,
+    Approved,
+}
+
+impl Status {
+    pub const ALL: [Status; 4] = [
+        Status::Untranslated,
+        Status::MachineTranslated,
+        Status::NeedsReview,
+        Status::Approved,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Status::Untranslated => "untranslated",
+            Status::MachineTranslated => "machine-translated",
+            Status::NeedsReview => "needs-review",
+            Status::Approved => "approved",
+        }
+    }
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Status {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Status::ALL
+            .into_iter()
+            .find(|x| x.as_str() == s.trim())
+            .ok_or_else(|| anyhow::anyhow!("{s:?} is not a translation status"))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Metadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<Status>,
+    /// When the unit last changed, as an RFC 3339 timestamp in UTC.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translator: Option<String>,
+    /// The [`source_hash`] of the base string this unit was translated from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_hash: Option<String>,
+}
+
+impl Metadata {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// Hashes the Fluent source of a unit's value and attributes.
+pub fn source_hash(unit: &TranslationUnit) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let mut write = |value: &str| {
+        for byte in value.bytes().chain(std::iter::once(0)) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    };
+
+    write(&pattern_source(&unit.main));
+    for (attr, value) in unit.attributes.iter() {
+        write(attr);
+        write(&pattern_source(value));
+    }
+
+    format!("{hash:016x}")
+}
+
+/// Formats a time as an RFC 3339 timestamp in UTC, to the second.
+pub fn timestamp(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or_default();
+    let (days, seconds) = (seconds / 86400, seconds % 86400);
+
+    // Civil date from days since the epoch, after Howard Hinnant's
+    // `civil_from_days`.
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::ir::{Pattern, TUIdentifier};
+
+    #[test]
+    fn timestamps_are_utc() {
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+        assert_eq!(timestamp(time), "2024-02-29T12:34:56Z");
+        assert_eq!(timestamp(SystemTime::UNIX_EPOCH), "1970-01-01T00:00:00Z");
+    }
+
+    #[test]
+    fn source_hash_tracks_the_value() {
+        let mut unit = TranslationUnit::new(TUIdentifier::try_from("welcome").unwrap());
+        unit.main = Pattern::text("Welcome!");
+        let hash = source_hash(&unit);
+        assert_eq!(hash.len(), 16);
+        assert_eq!(source_hash(&unit.clone()), hash);
+
+        unit.main = Pattern::text("Welcome back!");
+        assert_ne!(source_hash(&unit), hash);
+    }
+}
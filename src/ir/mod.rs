@@ -0,0 +1,781 @@
+//! Intermediate representation of our localisation project:
+//!
+//! Project -> Category -> TranslationUnitMap -> TranslationUnit
+//!
+//! The whole project can be dumped to a single JSON document with
+//! [`generate`] and loaded back with [`load_project_from_path`], for scripts
+//! that want to transform a project without linking against this crate.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    ops::{Deref, DerefMut},
+    path::Path,
+    str::FromStr,
+};
+
+use heck::ToSnakeCase;
+use icu::locid::LanguageIdentifier;
+use serde::{Deserialize, Serialize};
+
+use crate::{BTreeKeyedSet, Keyed, PathNode};
+
+pub mod metadata;
+pub mod pattern;
+
+pub use metadata::{Metadata, Status};
+pub use pattern::{Arguments, Element, Expression, Pattern, Select, Variant, VariantKey};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Project {
+    pub name: String,
+    pub default_locale: Option<LanguageIdentifier>,
+    pub categories: BTreeKeyedSet<CIdentifier, Category>,
+}
+
+impl Default for Project {
+    fn default() -> Self {
+        Self {
+            categories: BTreeKeyedSet::new(),
+            name: "Untitled".to_string(),
+            default_locale: None,
+        }
+    }
+}
+
+impl Deref for Project {
+    type Target = BTreeKeyedSet<CIdentifier, Category>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.categories
+    }
+}
+
+impl DerefMut for Project {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.categories
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Category {
+    pub key: CIdentifier,
+    pub name: String,
+    pub default_locale: LanguageIdentifier,
+    pub descriptions: BTreeMap<TUIdentifier, String>,
+    /// Notes about the category as a whole, like a Fluent resource comment.
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub sections: Vec<Section>,
+    pub translation_units: BTreeKeyedSet<LanguageIdentifier, TranslationUnitMap>,
+}
+
+/// Units grouped under a heading, like a Fluent group comment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Section {
+    pub heading: String,
+    pub keys: Vec<TUIdentifier>,
+}
+
+impl Keyed<CIdentifier> for Category {
+    fn key(&self) -> CIdentifier {
+        self.key.clone()
+    }
+}
+
+impl Category {
+    pub fn base_strings(&self) -> &TranslationUnitMap {
+        self.translation_units.get(&self.default_locale).unwrap()
+    }
+
+    pub fn ordered_locale_keys(&self) -> impl Iterator<Item = &LanguageIdentifier> {
+        std::iter::once(&self.default_locale)
+            .chain(self.keys().filter(|x| *x != &self.default_locale))
+    }
+
+    /// The keys of the given units in the order they are written: units
+    /// outside of any section first, then each section in turn.
+    pub fn ordered_keys<'a>(&'a self, units: &'a TranslationUnitMap) -> Vec<&'a TUIdentifier> {
+        let sectioned = self
+            .sections
+            .iter()
+            .flat_map(|x| x.keys.iter())
+            .collect::<BTreeSet<_>>();
+
+        units
+            .keys()
+            .filter(|x| !sectioned.contains(x))
+            .chain(
+                self.sections
+                    .iter()
+                    .flat_map(|x| x.keys.iter())
+                    .filter(|x| units.contains_key(*x)),
+            )
+            .collect()
+    }
+
+    /// The section that starts at the given key, when the units are written
+    /// in the order of [`Category::ordered_keys`].
+    pub fn section_starting_at(
+        &self,
+        units: &TranslationUnitMap,
+        key: &TUIdentifier,
+    ) -> Option<&Section> {
+        self.sections
+            .iter()
+            .find(|x| x.keys.iter().find(|x| units.contains_key(*x)) == Some(key))
+    }
+
+    pub fn ordered_tu_identity_keys(
+        &self,
+    ) -> impl Iterator<Item = (&TUIdentifier, Option<&TUIdentifier>)> {
+        let base = self.base_strings();
+
+        self.ordered_keys(base)
+            .into_iter()
+            .map(move |k| {
+                std::iter::once((k, None))
+                    .chain(base[k].attributes.keys().map(move |a| (k, Some(a))))
+            })
+            .flatten()
+    }
+}
+
+impl Deref for Category {
+    type Target = BTreeKeyedSet<LanguageIdentifier, TranslationUnitMap>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.translation_units
+    }
+}
+
+impl DerefMut for Category {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.translation_units
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TranslationUnitMap {
+    pub locale: LanguageIdentifier,
+    pub translation_units: BTreeKeyedSet<TUIdentifier, TranslationUnit>,
+}
+
+impl TranslationUnitMap {
+    pub fn new(locale: LanguageIdentifier) -> Self {
+        Self {
+            locale,
+            translation_units: Default::default(),
+        }
+    }
+}
+
+impl Keyed<LanguageIdentifier> for TranslationUnitMap {
+    fn key(&self) -> LanguageIdentifier {
+        self.locale.clone()
+    }
+}
+
+impl Eq for TranslationUnitMap {}
+
+impl PartialEq for TranslationUnitMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.locale == other.locale
+    }
+}
+
+impl PartialOrd for TranslationUnitMap {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        match self.locale.partial_cmp(&other.locale) {
+            Some(core::cmp::Ordering::Equal) => {}
+            ord => return ord,
+        }
+        Some(core::cmp::Ordering::Equal)
+    }
+}
+
+impl Ord for TranslationUnitMap {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.locale.cmp(&other.locale)
+    }
+}
+
+impl Deref for TranslationUnitMap {
+    type Target = BTreeMap<TUIdentifier, TranslationUnit>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.translation_units
+    }
+}
+
+impl DerefMut for TranslationUnitMap {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.translation_units
+    }
+}
+
+/// Whether a unit is looked up by the app, or only exists to be referenced
+/// from other units, like a Fluent term.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UnitKind {
+    #[default]
+    Message,
+    Term,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TranslationUnit {
+    /// Terms keep their leading `-`, so that they never clash with a message
+    /// of the same name.
+    pub key: TUIdentifier,
+    #[serde(default)]
+    pub kind: UnitKind,
+    pub main: Pattern,
+    pub attributes: BTreeMap<TUIdentifier, Pattern>,
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    pub metadata: Metadata,
+}
+
+impl TranslationUnit {
+    /// An empty unit. Formats without terms of their own carry them as keys
+    /// with a leading `-`, so those become terms.
+    pub fn new(key: TUIdentifier) -> Self {
+        Self {
+            kind: if key.starts_with('-') {
+                UnitKind::Term
+            } else {
+                UnitKind::Message
+            },
+            key,
+            main: Pattern::default(),
+            attributes: Default::default(),
+            metadata: Default::default(),
+        }
+    }
+
+    pub fn is_term(&self) -> bool {
+        self.kind == UnitKind::Term
+    }
+
+    /// The main value, or the value of the given attribute.
+    pub fn value(&self, attr: Option<&TUIdentifier>) -> Option<&Pattern> {
+        match attr {
+            Some(attr) => self.attributes.get(attr),
+            None => Some(&self.main),
+        }
+    }
+}
+
+impl Keyed<TUIdentifier> for TranslationUnit {
+    fn key(&self) -> TUIdentifier {
+        self.key.clone()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdentifierError {
+    /// Message identifiers follow Fluent's grammar: an ASCII letter followed
+    /// by letters, digits, `-` and `_`, with a leading `-` for terms.
+    Message(String),
+    /// Category identifiers are snake_case: a lowercase ASCII letter followed
+    /// by lowercase letters, digits and `_`.
+    Category(String),
+    /// Two different keys were sanitized into the same identifier.
+    Collision {
+        identifier: String,
+        keys: (String, String),
+    },
+}
+
+impl Display for IdentifierError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IdentifierError::Message(value) => write!(
+                f,
+                "{value:?} is not a valid message identifier; expected a letter followed by letters, digits, '-' or '_'"
+            ),
+            IdentifierError::Category(value) => write!(
+                f,
+                "{value:?} is not a valid category identifier; expected snake_case"
+            ),
+            IdentifierError::Collision {
+                identifier,
+                keys: (a, b),
+            } => write!(f, "{a:?} and {b:?} would both become {identifier}"),
+        }
+    }
+}
+
+impl std::error::Error for IdentifierError {}
+
+fn is_message_identifier(value: &str) -> bool {
+    let value = value.strip_prefix('-').unwrap_or(value);
+    let mut chars = value.chars();
+    chars.next().is_some_and(|x| x.is_ascii_alphabetic())
+        && chars.all(|x| x.is_ascii_alphanumeric() || x == '-' || x == '_')
+}
+
+fn is_category_identifier(value: &str) -> bool {
+    let mut chars = value.chars();
+    chars.next().is_some_and(|x| x.is_ascii_lowercase())
+        && chars.all(|x| x.is_ascii_lowercase() || x.is_ascii_digit() || x == '_')
+}
+
+/// Replaces runs of characters not matching `is_valid` with `separator`, and
+/// makes sure the result starts with a letter.
+fn sanitize(value: &str, separator: char, is_valid: impl Fn(char) -> bool) -> String {
+    let mut out = String::new();
+    for c in value.chars() {
+        if is_valid(c) {
+            out.push(c);
+        } else if !out.is_empty() && !out.ends_with(separator) {
+            out.push(separator);
+        }
+    }
+
+    let out = out.trim_end_matches(separator);
+    if out.starts_with(|x: char| x.is_ascii_alphabetic()) {
+        out.to_string()
+    } else if out.is_empty() {
+        "x".to_string()
+    } else {
+        format!("x{separator}{out}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+#[repr(transparent)]
+pub struct TUIdentifier(String);
+
+impl TUIdentifier {
+    /// Rewrites the value into a valid identifier, turning anything outside
+    /// of Fluent's grammar into dashes. Terms keep their leading `-`.
+    pub fn sanitize(value: &str) -> Self {
+        let (prefix, value) = match value.trim().strip_prefix('-') {
+            Some(x) => ("-", x),
+            None => ("", value.trim()),
+        };
+        let value = sanitize(value, '-', |x| {
+            x.is_ascii_alphanumeric() || x == '-' || x == '_'
+        });
+        TUIdentifier(format!("{prefix}{value}"))
+    }
+}
+
+impl Deref for TUIdentifier {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl FromStr for TUIdentifier {
+    type Err = IdentifierError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.try_into()
+    }
+}
+
+impl TryFrom<String> for TUIdentifier {
+    type Error = IdentifierError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if is_message_identifier(&value) {
+            Ok(TUIdentifier(value))
+        } else {
+            Err(IdentifierError::Message(value))
+        }
+    }
+}
+
+impl TryFrom<&String> for TUIdentifier {
+    type Error = IdentifierError;
+
+    fn try_from(value: &String) -> Result<Self, Self::Error> {
+        value.to_string().try_into()
+    }
+}
+
+impl TryFrom<&str> for TUIdentifier {
+    type Error = IdentifierError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.to_string().try_into()
+    }
+}
+
+impl From<TUIdentifier> for String {
+    fn from(value: TUIdentifier) -> Self {
+        value.0
+    }
+}
+
+impl<S: AsRef<str>> From<fluent_syntax::ast::Term<S>> for TUIdentifier {
+    fn from(value: fluent_syntax::ast::Term<S>) -> Self {
+        TUIdentifier(format!("-{}", value.id.name.as_ref()))
+    }
+}
+
+impl<S: AsRef<str>> From<&fluent_syntax::ast::Term<S>> for TUIdentifier {
+    fn from(value: &fluent_syntax::ast::Term<S>) -> Self {
+        TUIdentifier(format!("-{}", value.id.name.as_ref()))
+    }
+}
+
+impl<S: AsRef<str>> From<fluent_syntax::ast::Message<S>> for TUIdentifier {
+    fn from(value: fluent_syntax::ast::Message<S>) -> Self {
+        TUIdentifier(value.id.name.as_ref().to_string())
+    }
+}
+
+impl<S: AsRef<str>> From<&fluent_syntax::ast::Message<S>> for TUIdentifier {
+    fn from(value: &fluent_syntax::ast::Message<S>) -> Self {
+        TUIdentifier(value.id.name.as_ref().to_string())
+    }
+}
+
+impl<S: AsRef<str>> From<fluent_syntax::ast::Attribute<S>> for TUIdentifier {
+    fn from(value: fluent_syntax::ast::Attribute<S>) -> Self {
+        TUIdentifier(value.id.name.as_ref().to_string())
+    }
+}
+
+impl<S: AsRef<str>> From<&fluent_syntax::ast::Attribute<S>> for TUIdentifier {
+    fn from(value: &fluent_syntax::ast::Attribute<S>) -> Self {
+        TUIdentifier(value.id.name.as_ref().to_string())
+    }
+}
+
+impl Display for TUIdentifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+#[repr(transparent)]
+pub struct CIdentifier(String);
+
+impl CIdentifier {
+    /// Rewrites the value into snake_case, dropping anything that is not an
+    /// ASCII letter or digit.
+    pub fn sanitize(value: &str) -> Self {
+        let value = sanitize(&value.to_snake_case(), '_', |x| {
+            x.is_ascii_lowercase() || x.is_ascii_digit()
+        });
+        CIdentifier(value)
+    }
+}
+
+impl Deref for CIdentifier {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl FromStr for CIdentifier {
+    type Err = IdentifierError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.try_into()
+    }
+}
+
+impl TryFrom<String> for CIdentifier {
+    type Error = IdentifierError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if is_category_identifier(&value) {
+            Ok(CIdentifier(value))
+        } else {
+            Err(IdentifierError::Category(value))
+        }
+    }
+}
+
+impl TryFrom<&String> for CIdentifier {
+    type Error = IdentifierError;
+
+    fn try_from(value: &String) -> Result<Self, Self::Error> {
+        value.to_string().try_into()
+    }
+}
+
+impl TryFrom<&str> for CIdentifier {
+    type Error = IdentifierError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.to_string().try_into()
+    }
+}
+
+impl From<CIdentifier> for String {
+    fn from(value: CIdentifier) -> Self {
+        value.0
+    }
+}
+
+impl Display for CIdentifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Builds identifiers from the keys of imported files. Keys that are not
+/// valid identifiers are an error, unless the importer was asked to sanitize
+/// them, in which case they are rewritten and the renames are recorded. A
+/// rename onto an identifier that another key of the same category, or
+/// attribute of the same message, already has is an error.
+#[derive(Debug, Default)]
+pub struct Keys {
+    sanitize: bool,
+    renamed: BTreeMap<String, String>,
+    /// The key each identifier was made from, when sanitizing. Messages are
+    /// scoped by their category and attributes by their message too.
+    units: BTreeMap<(CIdentifier, Option<TUIdentifier>, String), String>,
+    categories: BTreeMap<String, String>,
+}
+
+impl Keys {
+    pub fn strict() -> Self {
+        Self::default()
+    }
+
+    pub fn sanitizing() -> Self {
+        Self {
+            sanitize: true,
+            ..Default::default()
+        }
+    }
+
+    /// The identifier of a message of the given category.
+    pub fn unit(
+        &mut self,
+        category: &CIdentifier,
+        value: &str,
+    ) -> Result<TUIdentifier, IdentifierError> {
+        self.scoped_unit(category, None, value)
+    }
+
+    /// The identifier of an attribute of the given message.
+    pub fn attribute(
+        &mut self,
+        category: &CIdentifier,
+        key: &TUIdentifier,
+        value: &str,
+    ) -> Result<TUIdentifier, IdentifierError> {
+        self.scoped_unit(category, Some(key), value)
+    }
+
+    fn scoped_unit(
+        &mut self,
+        category: &CIdentifier,
+        key: Option<&TUIdentifier>,
+        value: &str,
+    ) -> Result<TUIdentifier, IdentifierError> {
+        let out = match TUIdentifier::try_from(value) {
+            Err(_) if self.sanitize => TUIdentifier::sanitize(value),
+            x => x?,
+        };
+        if self.sanitize {
+            let scope = (category.clone(), key.cloned(), out.to_string());
+            claim(&mut self.units, &mut self.renamed, scope, value, &out)?;
+        }
+        Ok(out)
+    }
+
+    pub fn category(&mut self, value: &str) -> Result<CIdentifier, IdentifierError> {
+        let out = match CIdentifier::try_from(value) {
+            Err(_) if self.sanitize => CIdentifier::sanitize(value),
+            x => x?,
+        };
+        if self.sanitize {
+            claim(
+                &mut self.categories,
+                &mut self.renamed,
+                out.to_string(),
+                value,
+                &out,
+            )?;
+        }
+        Ok(out)
+    }
+
+    /// The keys that were rewritten, as `(original, sanitized)`.
+    pub fn renamed(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.renamed.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+/// Records that `identifier`, taken under `scope`, was made from `key`,
+/// failing if a different key already took it.
+fn claim<K: Ord>(
+    taken: &mut BTreeMap<K, String>,
+    renamed: &mut BTreeMap<String, String>,
+    scope: K,
+    key: &str,
+    identifier: &str,
+) -> Result<(), IdentifierError> {
+    match taken.get(&scope) {
+        Some(existing) if existing != key => Err(IdentifierError::Collision {
+            identifier: identifier.to_string(),
+            keys: (existing.clone(), key.to_string()),
+        }),
+        Some(_) => Ok(()),
+        None => {
+            taken.insert(scope, key.to_string());
+            if key != identifier {
+                renamed.insert(key.to_string(), identifier.to_string());
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Dumps the whole project as a single JSON document.
+pub fn generate(input: Project) -> Result<PathNode, serde_json::Error> {
+    let mut out = serde_json::to_vec_pretty(&input)?;
+    out.push(b'\n');
+    Ok(PathNode::File(out))
+}
+
+/// Loads a project dumped by [`generate`].
+pub fn load_project_from_path(path: &Path) -> anyhow::Result<Project> {
+    let file = std::fs::File::open(path)?;
+    Ok(serde_json::from_reader(std::io::BufReader::new(file))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_round_trip() {
+        let json = r#"{
+            "name": "Sample",
+            "default-locale": "en",
+            "categories": {
+                "stale_key": {
+                    "key": "core",
+                    "name": "Core",
+                    "default-locale": "en",
+                    "descriptions": { "welcome": "Shown on launch" },
+                    "translation-units": {
+                        "en": {
+                            "locale": "en",
+                            "translation-units": {
+                                "welcome": {
+                                    "key": "welcome",
+                                    "main": [
+                                        { "text": "Welcome, " },
+                                        { "placeable": { "variable": "user" } },
+                                        { "text": "!" }
+                                    ],
+                                    "attributes": { "title": [{ "text": "Hello" }] }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }"#;
+
+        let project: Project = serde_json::from_str(json).unwrap();
+        let category = project
+            .get(&CIdentifier::try_from("core").unwrap())
+            .unwrap();
+        let unit = &category.base_strings()[&TUIdentifier::try_from("welcome").unwrap()];
+        assert_eq!(
+            unit.main.elements[1],
+            Element::Placeable(Expression::Variable("user".into()))
+        );
+
+        let value = serde_json::to_value(&project).unwrap();
+        assert_eq!(value["categories"]["core"]["key"], "core");
+        let again: Project = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&again).unwrap(), value);
+    }
+
+    #[test]
+    fn identifiers_are_validated() {
+        assert!(TUIdentifier::try_from("welcome-title_2").is_ok());
+        assert!(TUIdentifier::try_from("-brand-name").is_ok());
+        assert!(TUIdentifier::try_from("2nd-step").is_err());
+        assert!(TUIdentifier::try_from("hello world").is_err());
+        assert!(TUIdentifier::try_from("größe").is_err());
+        assert!(TUIdentifier::try_from("").is_err());
+
+        assert!(CIdentifier::try_from("core_2").is_ok());
+        assert!(CIdentifier::try_from("Core").is_err());
+        assert!(CIdentifier::try_from("core-app").is_err());
+    }
+
+    #[test]
+    fn sanitize_keys() {
+        let core = CIdentifier::try_from("core").unwrap();
+        let mut keys = Keys::sanitizing();
+        assert_eq!(&*keys.unit(&core, "welcome").unwrap(), "welcome");
+        assert_eq!(
+            &*keys.unit(&core, " Hello, world! ").unwrap(),
+            "Hello-world"
+        );
+        assert_eq!(&*keys.unit(&core, "2nd step").unwrap(), "x-2nd-step");
+        assert_eq!(&*keys.unit(&core, "-größe").unwrap(), "-gr-e");
+        assert_eq!(
+            &*keys.category("Settings Screen").unwrap(),
+            "settings_screen"
+        );
+        assert_eq!(
+            keys.renamed().collect::<Vec<_>>(),
+            [
+                (" Hello, world! ", "Hello-world"),
+                ("-größe", "-gr-e"),
+                ("2nd step", "x-2nd-step"),
+                ("Settings Screen", "settings_screen"),
+            ]
+        );
+
+        assert!(Keys::strict().unit(&core, "2nd step").is_err());
+
+        let mut keys = Keys::sanitizing();
+        assert_eq!(&*keys.unit(&core, "foo.bar").unwrap(), "foo-bar");
+        assert!(keys.unit(&core, "foo.bar").is_ok());
+        assert!(matches!(
+            keys.unit(&core, "foo-bar"),
+            Err(IdentifierError::Collision { .. })
+        ));
+        assert_eq!(&*keys.category("foo_bar").unwrap(), "foo_bar");
+        assert!(matches!(
+            keys.category("Foo Bar"),
+            Err(IdentifierError::Collision { .. })
+        ));
+    }
+
+    #[test]
+    fn sanitized_keys_are_scoped() {
+        let core = CIdentifier::try_from("core").unwrap();
+        let settings = CIdentifier::try_from("settings").unwrap();
+        let mut keys = Keys::sanitizing();
+
+        let key = keys.unit(&core, "foo.bar").unwrap();
+        assert_eq!(&*keys.unit(&settings, "foo-bar").unwrap(), "foo-bar");
+        assert_eq!(&*keys.attribute(&core, &key, "foo-bar").unwrap(), "foo-bar");
+        assert!(matches!(
+            keys.attribute(&core, &key, "foo bar"),
+            Err(IdentifierError::Collision { .. })
+        ));
+    }
+}
@@ -0,0 +1,436 @@
+//! The structure of a translation unit's value, modelled on Fluent patterns:
+//! text interleaved with placeables (variable, message and term references,
+//! function calls and literals) and select expressions.
+//!
+//! Text is stored unescaped. Escaping only happens when a pattern is written
+//! back out as Fluent source by [`Pattern::to_fluent`].
+
+use fluent_syntax::ast;
+use serde::{Deserialize, Serialize};
+
+use crate::flt::render::unescape_literal;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Pattern {
+    pub elements: Vec<Element>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Element {
+    Text(String),
+    Placeable(Expression),
+    Select(Select),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Expression {
+    /// A string literal. Literals in a pattern itself are merged into its
+    /// text, so these only appear as arguments.
+    String(String),
+    Number(String),
+    Variable(String),
+    Message {
+        id: String,
+        attribute: Option<String>,
+    },
+    /// A term reference, with the id as written after the leading `-`.
+    Term {
+        id: String,
+        attribute: Option<String>,
+        arguments: Option<Arguments>,
+    },
+    Function {
+        name: String,
+        arguments: Arguments,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Arguments {
+    pub positional: Vec<Expression>,
+    pub named: Vec<(String, Expression)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Select {
+    pub selector: Expression,
+    pub variants: Vec<Variant>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Variant {
+    pub key: VariantKey,
+    pub default: bool,
+    pub value: Pattern,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum VariantKey {
+    Identifier(String),
+    Number(String),
+}
+
+impl VariantKey {
+    /// A key from a plural category or an exact number such as `0`.
+    pub fn new(value: &str) -> Self {
+        if value.parse::<f64>().is_ok() {
+            VariantKey::Number(value.to_string())
+        } else {
+            VariantKey::Identifier(value.to_string())
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            VariantKey::Identifier(x) | VariantKey::Number(x) => x,
+        }
+    }
+}
+
+impl Expression {
+    /// The key of the translation unit a message or term reference points
+    /// to, and the referenced attribute.
+    pub fn reference(&self) -> Option<(String, Option<&str>)> {
+        match self {
+            Expression::Message { id, attribute } => Some((id.clone(), attribute.as_deref())),
+            Expression::Term { id, attribute, .. } => {
+                Some((format!("-{id}"), attribute.as_deref()))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Select {
+    /// The default variant, which Fluent requires every select to have.
+    pub fn default_variant(&self) -> Option<&Variant> {
+        self.variants.iter().find(|x| x.default)
+    }
+}
+
+impl Pattern {
+    pub fn text(value: &str) -> Self {
+        let mut out = Self::default();
+        out.push_text(value);
+        out
+    }
+
+    /// Whether the pattern has nothing but whitespace, as is the case for
+    /// the value of a message that only has attributes.
+    pub fn is_empty(&self) -> bool {
+        self.elements
+            .iter()
+            .all(|x| matches!(x, Element::Text(text) if text.trim().is_empty()))
+    }
+
+    /// Appends an element, merging adjacent text.
+    pub fn push(&mut self, element: Element) {
+        match (self.elements.last_mut(), element) {
+            (_, Element::Text(text)) if text.is_empty() => {}
+            (Some(Element::Text(a)), Element::Text(b)) => a.push_str(&b),
+            (_, element) => self.elements.push(element),
+        }
+    }
+
+    pub fn push_text(&mut self, value: &str) {
+        self.push(Element::Text(value.to_string()));
+    }
+
+    pub fn push_variable(&mut self, name: &str) {
+        self.push(Element::Placeable(Expression::Variable(name.to_string())));
+    }
+
+    pub fn extend(&mut self, other: Pattern) {
+        for element in other.elements {
+            self.push(element);
+        }
+    }
+
+    /// Builds a select on `selector` from patterns keyed by variant key.
+    /// `other`, or failing that the last variant, is the default.
+    pub fn select(selector: Expression, variants: Vec<(String, Pattern)>) -> Self {
+        let default = variants
+            .iter()
+            .position(|(key, _)| key == "other")
+            .unwrap_or(variants.len().saturating_sub(1));
+
+        Self {
+            elements: vec![Element::Select(Select {
+                selector,
+                variants: variants
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, (key, value))| Variant {
+                        key: VariantKey::new(&key),
+                        default: i == default,
+                        value,
+                    })
+                    .collect(),
+            })],
+        }
+    }
+
+    /// The Fluent AST of the pattern. Text that Fluent would read as syntax
+    /// is written as string literals.
+    pub fn to_fluent(&self) -> ast::Pattern<String> {
+        let mut elements = vec![];
+        let mut line_start = true;
+
+        for element in self.elements.iter() {
+            match element {
+                Element::Text(text) => push_fluent_text(&mut elements, text, &mut line_start),
+                Element::Placeable(x) => {
+                    elements.push(ast::PatternElement::Placeable {
+                        expression: ast::Expression::Inline(x.to_fluent()),
+                    });
+                    line_start = false;
+                }
+                Element::Select(x) => {
+                    elements.push(ast::PatternElement::Placeable {
+                        expression: ast::Expression::Select {
+                            selector: x.selector.to_fluent(),
+                            variants: x
+                                .variants
+                                .iter()
+                                .map(|variant| ast::Variant {
+                                    key: match &variant.key {
+                                        VariantKey::Identifier(name) => {
+                                            ast::VariantKey::Identifier { name: name.clone() }
+                                        }
+                                        VariantKey::Number(value) => {
+                                            ast::VariantKey::NumberLiteral {
+                                                value: value.clone(),
+                                            }
+                                        }
+                                    },
+                                    value: variant.value.to_fluent(),
+                                    default: variant.default,
+                                })
+                                .collect(),
+                        },
+                    });
+                    line_start = false;
+                }
+            }
+        }
+
+        ast::Pattern { elements }
+    }
+}
+
+fn string_literal(value: &str) -> ast::PatternElement<String> {
+    ast::PatternElement::Placeable {
+        expression: ast::Expression::Inline(ast::InlineExpression::StringLiteral {
+            value: value.replace('\\', "\\\\").replace('"', "\\\""),
+        }),
+    }
+}
+
+/// Whether Fluent would read `c` as syntax rather than text, so that it has
+/// to be written as a string literal: braces anywhere, and `[`, `*` and `.`
+/// at the start of a line, where they would start a variant or attribute.
+/// `line_start` tracks whether `c` starts a line, across calls.
+pub(crate) fn is_fluent_syntax(c: char, line_start: &mut bool) -> bool {
+    let out = match c {
+        '{' | '}' => true,
+        '[' | '*' | '.' => *line_start,
+        _ => false,
+    };
+
+    if c == '\n' {
+        *line_start = true;
+    } else if c != ' ' {
+        *line_start = false;
+    }
+
+    out
+}
+
+/// Appends text as Fluent text elements, one per line so that the
+/// serializer indents every line, and with [syntax](is_fluent_syntax) as
+/// string literals.
+fn push_fluent_text(
+    elements: &mut Vec<ast::PatternElement<String>>,
+    value: &str,
+    line_start: &mut bool,
+) {
+    let mut text = String::new();
+
+    for c in value.chars() {
+        if is_fluent_syntax(c, line_start) {
+            if !text.is_empty() {
+                elements.push(ast::PatternElement::TextElement {
+                    value: std::mem::take(&mut text),
+                });
+            }
+            elements.push(string_literal(&c.to_string()));
+        } else {
+            text.push(c);
+        }
+
+        if c == '\n' {
+            elements.push(ast::PatternElement::TextElement {
+                value: std::mem::take(&mut text),
+            });
+        }
+    }
+
+    if !text.is_empty() {
+        elements.push(ast::PatternElement::TextElement { value: text });
+    }
+}
+
+impl Expression {
+    pub fn to_fluent(&self) -> ast::InlineExpression<String> {
+        match self {
+            Expression::String(value) => ast::InlineExpression::StringLiteral {
+                value: value.replace('\\', "\\\\").replace('"', "\\\""),
+            },
+            Expression::Number(value) => ast::InlineExpression::NumberLiteral {
+                value: value.clone(),
+            },
+            Expression::Variable(name) => ast::InlineExpression::VariableReference {
+                id: ast::Identifier { name: name.clone() },
+            },
+            Expression::Message { id, attribute } => ast::InlineExpression::MessageReference {
+                id: ast::Identifier { name: id.clone() },
+                attribute: attribute.clone().map(|name| ast::Identifier { name }),
+            },
+            Expression::Term {
+                id,
+                attribute,
+                arguments,
+            } => ast::InlineExpression::TermReference {
+                id: ast::Identifier { name: id.clone() },
+                attribute: attribute.clone().map(|name| ast::Identifier { name }),
+                arguments: arguments.as_ref().map(Arguments::to_fluent),
+            },
+            Expression::Function { name, arguments } => ast::InlineExpression::FunctionReference {
+                id: ast::Identifier { name: name.clone() },
+                arguments: arguments.to_fluent(),
+            },
+        }
+    }
+}
+
+impl Arguments {
+    fn to_fluent(&self) -> ast::CallArguments<String> {
+        ast::CallArguments {
+            positional: self.positional.iter().map(Expression::to_fluent).collect(),
+            named: self
+                .named
+                .iter()
+                .map(|(name, value)| ast::NamedArgument {
+                    name: ast::Identifier { name: name.clone() },
+                    value: value.to_fluent(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<&ast::Pattern<String>> for Pattern {
+    fn from(value: &ast::Pattern<String>) -> Self {
+        let mut out = Pattern::default();
+        for element in value.elements.iter() {
+            match element {
+                ast::PatternElement::TextElement { value } => out.push_text(value),
+                ast::PatternElement::Placeable { expression } => {
+                    push_expression(&mut out, expression)
+                }
+            }
+        }
+        out
+    }
+}
+
+fn push_expression(out: &mut Pattern, expression: &ast::Expression<String>) {
+    match expression {
+        ast::Expression::Inline(ast::InlineExpression::StringLiteral { value }) => {
+            out.push_text(&unescape_literal(value))
+        }
+        // Placeables within placeables are the same as their content
+        ast::Expression::Inline(ast::InlineExpression::Placeable { expression }) => {
+            push_expression(out, expression)
+        }
+        ast::Expression::Inline(x) => out.push(Element::Placeable(Expression::from(x))),
+        ast::Expression::Select { selector, variants } => out.push(Element::Select(Select {
+            selector: Expression::from(selector),
+            variants: variants
+                .iter()
+                .map(|x| Variant {
+                    key: match &x.key {
+                        ast::VariantKey::Identifier { name } => {
+                            VariantKey::Identifier(name.clone())
+                        }
+                        ast::VariantKey::NumberLiteral { value } => {
+                            VariantKey::Number(value.clone())
+                        }
+                    },
+                    default: x.default,
+                    value: Pattern::from(&x.value),
+                })
+                .collect(),
+        })),
+    }
+}
+
+impl From<&ast::InlineExpression<String>> for Expression {
+    fn from(value: &ast::InlineExpression<String>) -> Self {
+        match value {
+            ast::InlineExpression::StringLiteral { value } => {
+                Expression::String(unescape_literal(value))
+            }
+            ast::InlineExpression::NumberLiteral { value } => Expression::Number(value.clone()),
+            ast::InlineExpression::VariableReference { id } => {
+                Expression::Variable(id.name.clone())
+            }
+            ast::InlineExpression::MessageReference { id, attribute } => Expression::Message {
+                id: id.name.clone(),
+                attribute: attribute.as_ref().map(|x| x.name.clone()),
+            },
+            ast::InlineExpression::TermReference {
+                id,
+                attribute,
+                arguments,
+            } => Expression::Term {
+                id: id.name.clone(),
+                attribute: attribute.as_ref().map(|x| x.name.clone()),
+                arguments: arguments.as_ref().map(Arguments::from),
+            },
+            ast::InlineExpression::FunctionReference { id, arguments } => Expression::Function {
+                name: id.name.clone(),
+                arguments: Arguments::from(arguments),
+            },
+            ast::InlineExpression::Placeable { expression } => match &**expression {
+                ast::Expression::Inline(x) => Expression::from(x),
+                // A select can only be an argument in the most contrived
+                // patterns, so only its default variant is kept.
+                ast::Expression::Select { variants, .. } => Expression::String(
+                    variants
+                        .iter()
+                        .find(|x| x.default)
+                        .map(|x| crate::flt::pattern_source(&Pattern::from(&x.value)))
+                        .unwrap_or_default(),
+                ),
+            },
+        }
+    }
+}
+
+impl From<&ast::CallArguments<String>> for Arguments {
+    fn from(value: &ast::CallArguments<String>) -> Self {
+        Arguments {
+            positional: value.positional.iter().map(Expression::from).collect(),
+            named: value
+                .named
+                .iter()
+                .map(|x| (x.name.name.clone(), Expression::from(&x.value)))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,347 @@
+//! Kotlin Multiplatform classes with typed accessors.
+//!
+//! Each category becomes a class with a property per message without
+//! variables and a function per message with them, as in [`crate::ts`], and
+//! `Strings.kt` holds a `Strings` class with every category. These go in the
+//! `commonMain` source set and look strings up by their Android resource name
+//! through a `StringSource`, which each platform implements.
+//!
+//! `androidMain` gets an implementation backed by the Android string resources
+//! written by [`crate::android`], and a `Strings(context)` function to create
+//! one. The files are in a package named after the project, which should be
+//! the app's namespace so that `R` resolves. Output goes in the module's `src`
+//! directory.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use fluent_syntax::parser::ParserError;
+use heck::{ToLowerCamelCase, ToPascalCase, ToSnakeCase};
+
+use crate::{
+    android,
+    codegen::{self, Accessor},
+    flt::render,
+    ir::{Category, Project},
+    plural::PluralSelect,
+    PathNode,
+};
+
+const HEADER: &str = "// Generated by stringly. Do not edit.\n";
+
+const KEYWORDS: &[&str] = &[
+    "as",
+    "break",
+    "class",
+    "continue",
+    "do",
+    "else",
+    "false",
+    "for",
+    "fun",
+    "if",
+    "in",
+    "interface",
+    "is",
+    "null",
+    "object",
+    "package",
+    "return",
+    "super",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typealias",
+    "typeof",
+    "val",
+    "var",
+    "when",
+    "while",
+];
+
+fn ident(value: &str) -> String {
+    let value = value.to_lower_camel_case();
+    if KEYWORDS.contains(&value.as_str()) {
+        format!("`{value}`")
+    } else {
+        value
+    }
+}
+
+fn package(input: &Project) -> String {
+    input.name.to_snake_case().replace('_', "")
+}
+
+fn write_doc(out: &mut String, description: Option<&String>) {
+    let Some(description) = description else {
+        return;
+    };
+
+    out.push_str("    /**\n");
+    for line in description.lines() {
+        out.push_str(&format!("     * {}\n", line.replace("*/", "* /")));
+    }
+    out.push_str("     */\n");
+}
+
+fn generate_accessor(category_id: &str, category: &Category, accessor: &Accessor) -> String {
+    let pattern = accessor.pattern(category);
+    let name = android::resource_name(category_id, &accessor.id, accessor.attr.as_ref());
+
+    let mut out = String::new();
+    write_doc(&mut out, accessor.description.as_ref());
+
+    if accessor.variables.is_empty() {
+        out.push_str(&format!(
+            "    val {}: String\n        get() = source.getString(\"{name}\")\n",
+            ident(&accessor.name())
+        ));
+        return out;
+    }
+
+    let params = accessor
+        .variables
+        .iter()
+        .map(|(name, kind)| {
+            let ty = match kind {
+                render::VariableKind::String => "String",
+                render::VariableKind::Number => "Int",
+            };
+            format!("{}: {ty}", ident(name))
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let args = accessor
+        .variables
+        .iter()
+        .map(|(name, _)| format!(", {}", ident(name)))
+        .collect::<String>();
+
+    let call = match PluralSelect::from_pattern(pattern) {
+        Some(select) => format!(
+            "source.getQuantityString(\"{name}\", {}{args})",
+            ident(select.variable)
+        ),
+        None => format!("source.getString(\"{name}\"{args})"),
+    };
+    out.push_str(&format!(
+        "    fun {}({params}): String =\n        {call}\n",
+        ident(&accessor.name())
+    ));
+
+    out
+}
+
+fn generate_class(package: &str, category_id: &str, category: &Category) -> String {
+    let mut out = format!(
+        "{HEADER}\npackage {package}\n\nclass {}(private val source: StringSource) {{\n",
+        category_id.to_pascal_case()
+    );
+
+    for (i, accessor) in codegen::accessors(category).iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        out.push_str(&generate_accessor(category_id, category, accessor));
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+fn generate_source(package: &str) -> String {
+    format!(
+        concat!(
+            "{}\npackage {}\n\n",
+            "/**\n",
+            " * Looks strings up by their Android resource name and formats them with\n",
+            " * printf-style arguments.\n",
+            " */\n",
+            "interface StringSource {{\n",
+            "    fun getString(name: String, vararg args: Any): String\n\n",
+            "    fun getQuantityString(name: String, quantity: Int, vararg args: Any): String\n",
+            "}}\n",
+        ),
+        HEADER, package
+    )
+}
+
+fn generate_index(package: &str, input: &Project) -> String {
+    let mut out =
+        format!("{HEADER}\npackage {package}\n\nclass Strings(source: StringSource) {{\n");
+
+    for category_id in input.categories.keys() {
+        out.push_str(&format!(
+            "    val {} = {}(source)\n",
+            ident(category_id),
+            category_id.to_pascal_case()
+        ));
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+/// An override of a `StringSource` function that looks `name` up among the
+/// resources of the given kind.
+fn write_lookup(
+    out: &mut String,
+    function: &str,
+    params: &str,
+    call: &str,
+    kind: &str,
+    names: &BTreeSet<String>,
+) {
+    out.push_str(&format!(
+        "    override fun {function}({params}vararg args: Any): String {{\n        val id = when (name) {{\n"
+    ));
+    for name in names {
+        out.push_str(&format!("            \"{name}\" -> R.{kind}.{name}\n"));
+    }
+    out.push_str(&format!(
+        "            else -> throw IllegalArgumentException(\"No {kind} resource $name\")\n        }}\n        return {call}\n    }}\n"
+    ));
+}
+
+/// The Android `StringSource`, mapping resource names to their `R` ids so
+/// that a missing resource fails to compile.
+fn generate_android_source(package: &str, input: &Project) -> String {
+    let mut strings = BTreeSet::new();
+    let mut plurals = BTreeSet::new();
+    for (category_id, category) in input.categories.iter() {
+        for accessor in codegen::accessors(category) {
+            let name = android::resource_name(category_id, &accessor.id, accessor.attr.as_ref());
+            match PluralSelect::from_pattern(accessor.pattern(category)) {
+                Some(_) => plurals.insert(name),
+                None => strings.insert(name),
+            };
+        }
+    }
+
+    let mut out = format!(
+        concat!(
+            "{}\npackage {}\n\nimport android.content.Context\n\n",
+            "fun Strings(context: Context) = Strings(AndroidStringSource(context))\n\n",
+            "class AndroidStringSource(private val context: Context) : StringSource {{\n",
+        ),
+        HEADER, package
+    );
+
+    write_lookup(
+        &mut out,
+        "getString",
+        "name: String, ",
+        "context.getString(id, *args)",
+        "string",
+        &strings,
+    );
+    out.push('\n');
+    write_lookup(
+        &mut out,
+        "getQuantityString",
+        "name: String, quantity: Int, ",
+        "context.resources.getQuantityString(id, quantity, *args)",
+        "plurals",
+        &plurals,
+    );
+
+    out.push_str("}\n");
+    out
+}
+
+pub fn generate(input: Project) -> Result<PathNode, ParserError> {
+    let package = package(&input);
+    let mut common = BTreeMap::new();
+
+    for (category_id, category) in input.categories.iter() {
+        common.insert(
+            format!("{}.kt", category_id.to_pascal_case()),
+            PathNode::File(generate_class(&package, category_id, category).into_bytes()),
+        );
+    }
+
+    common.insert(
+        "StringSource.kt".into(),
+        PathNode::File(generate_source(&package).into_bytes()),
+    );
+    common.insert(
+        "Strings.kt".into(),
+        PathNode::File(generate_index(&package, &input).into_bytes()),
+    );
+
+    let android = BTreeMap::from([(
+        "AndroidStringSource.kt".to_string(),
+        PathNode::File(generate_android_source(&package, &input).into_bytes()),
+    )]);
+
+    let source_set = |files| {
+        PathNode::Directory(BTreeMap::from([(
+            "kotlin".to_string(),
+            PathNode::Directory(files),
+        )]))
+    };
+    Ok(PathNode::Directory(BTreeMap::from([
+        ("commonMain".to_string(), source_set(common)),
+        ("androidMain".to_string(), source_set(android)),
+    ])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flt::test_project;
+
+    #[test]
+    fn common_classes_and_android_source() {
+        let project = test_project(&[(
+            "en",
+            concat!(
+                "title = Files\n",
+                "files = { $count ->\n",
+                "    [one] One file in { $folder }\n",
+                "   *[other] { $count } files in { $folder }\n",
+                "}\n",
+            ),
+        )]);
+        let tree = generate(project).unwrap();
+
+        assert_eq!(
+            tree.text("commonMain/kotlin/Core.kt"),
+            concat!(
+                "// Generated by stringly. Do not edit.\n\n",
+                "package test\n\n",
+                "class Core(private val source: StringSource) {\n",
+                "    fun files(count: Int, folder: String): String =\n",
+                "        source.getQuantityString(\"core__files\", count, count, folder)\n\n",
+                "    val title: String\n",
+                "        get() = source.getString(\"core__title\")\n",
+                "}\n",
+            )
+        );
+        assert_eq!(
+            tree.text("androidMain/kotlin/AndroidStringSource.kt"),
+            concat!(
+                "// Generated by stringly. Do not edit.\n\n",
+                "package test\n\n",
+                "import android.content.Context\n\n",
+                "fun Strings(context: Context) = Strings(AndroidStringSource(context))\n\n",
+                "class AndroidStringSource(private val context: Context) : StringSource {\n",
+                "    override fun getString(name: String, vararg args: Any): String {\n",
+                "        val id = when (name) {\n",
+                "            \"core__title\" -> R.string.core__title\n",
+                "            else -> throw IllegalArgumentException(\"No string resource $name\")\n",
+                "        }\n",
+                "        return context.getString(id, *args)\n",
+                "    }\n\n",
+                "    override fun getQuantityString(name: String, quantity: Int, vararg args: Any): String {\n",
+                "        val id = when (name) {\n",
+                "            \"core__files\" -> R.plurals.core__files\n",
+                "            else -> throw IllegalArgumentException(\"No plurals resource $name\")\n",
+                "        }\n",
+                "        return context.resources.getQuantityString(id, quantity, *args)\n",
+                "    }\n",
+                "}\n",
+            )
+        );
+    }
+}
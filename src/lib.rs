@@ -5,10 +5,33 @@ use std::{
     path::Path,
 };
 
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub mod android;
+pub mod apple;
+pub mod arb;
+pub mod codegen;
+pub mod csv;
 pub mod flt;
+pub mod i18next;
 pub mod ir;
+pub mod kotlin;
+pub mod linguist;
+pub mod messageformat;
+pub mod ods;
+pub mod plural;
+pub mod po;
+pub mod printf;
+pub mod properties;
+pub mod rails;
+pub mod resx;
+pub mod rust;
+pub mod swift;
+pub mod tmx;
 pub mod translate;
 pub mod ts;
+pub mod xcstrings;
+pub mod xliff;
 pub mod xlsx;
 
 pub enum PathNode {
@@ -76,6 +99,21 @@ impl PathNode {
     }
 }
 
+#[cfg(test)]
+impl PathNode {
+    /// The text of the file at a `/`-separated path below this directory.
+    pub(crate) fn text(&self, path: &str) -> String {
+        let node = path.split('/').fold(self, |node, name| match node {
+            PathNode::Directory(tree) => &tree[name],
+            PathNode::File(_) => panic!("{name} is not in a directory"),
+        });
+        match node {
+            PathNode::File(data) => String::from_utf8(data.clone()).unwrap(),
+            PathNode::Directory(_) => panic!("{path} is a directory"),
+        }
+    }
+}
+
 fn write_directory(prefix: &Path, tree: BTreeMap<String, PathNode>) -> std::io::Result<()> {
     for (k, v) in tree.into_iter() {
         let path = prefix.join(&k);
@@ -169,6 +207,32 @@ where
     }
 }
 
+/// Serialized as a map, which makes for friendlier JSON than a list.
+impl<K, V> Serialize for BTreeKeyedSet<K, V>
+where
+    K: Serialize,
+    V: Serialize + Keyed<K>,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.map.serialize(serializer)
+    }
+}
+
+/// The keys of the map are only there for readability; every value is
+/// re-keyed by its own key.
+impl<'de, K, V> Deserialize<'de> for BTreeKeyedSet<K, V>
+where
+    K: Deserialize<'de> + Ord,
+    V: Deserialize<'de> + Keyed<K>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let map = BTreeMap::<K, V>::deserialize(deserializer)?;
+        Ok(Self {
+            map: map.into_values().map(|v| (v.key(), v)).collect(),
+        })
+    }
+}
+
 pub trait Keyed<V> {
     fn key(&self) -> V;
 }
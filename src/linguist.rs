@@ -0,0 +1,590 @@
+//! Qt Linguist `.ts` files, one per locale, with a `<context>` per category
+//! and messages identified by their `id`.
+//!
+//! Arguments are numbered placeables (`%1`) ordered by the base strings the
+//! same way as [`crate::printf`]. Plural selects become `numerus="yes"`
+//! messages whose count is `%n`, with one `<numerusform>` per gettext plural
+//! form of the language, which is also the order Qt uses. Attributes are
+//! stored as `<message>__<attribute>` ids.
+//!
+//! Translated files are merged back into the project they were generated
+//! from, mapping `%1` and `%n` back to the variables of the base string.
+
+use std::{collections::BTreeMap, path::Path, str::FromStr};
+
+use fluent_syntax::parser::ParserError;
+use html_escape::{encode_double_quoted_attribute, encode_text};
+use icu::locid::LanguageIdentifier;
+use quick_xml::events::{BytesStart, Event};
+
+use crate::{
+    flt::render,
+    ir::{Category, Keys, Pattern, Project, TranslationUnit, TranslationUnitMap},
+    plural::{self, PluralSelect},
+    printf, xliff, PathNode,
+};
+
+fn qt_locale(locale: &LanguageIdentifier) -> String {
+    locale.to_string().replace('-', "_")
+}
+
+/// Renders segments with `%1`-style placeables. `count` is the variable of
+/// the message's plural select, if any, which is written as `%n`.
+fn format_segments(
+    segments: &[render::Segment],
+    args: &mut Vec<String>,
+    count: Option<&str>,
+) -> String {
+    segments
+        .iter()
+        .map(|x| match x {
+            render::Segment::Text(text) => text.clone(),
+            render::Segment::Variable(name, _) if Some(name.as_str()) == count => "%n".to_string(),
+            render::Segment::Variable(name, _) => {
+                format!("%{}", render::argument_index(args, name) + 1)
+            }
+        })
+        .collect()
+}
+
+enum Translation {
+    Single(String),
+    Numerus(Vec<String>),
+}
+
+fn translation(
+    pattern: &Pattern,
+    units: &TranslationUnitMap,
+    args: &mut Vec<String>,
+    count: Option<&str>,
+) -> Translation {
+    match (PluralSelect::from_pattern(pattern), count) {
+        (Some(select), Some(_)) => Translation::Numerus(
+            plural::gettext_plurals(&units.locale)
+                .categories
+                .iter()
+                .map(|category| {
+                    let segments = render::segments(&select.expand(category), units);
+                    format_segments(&segments, args, Some(select.variable))
+                })
+                .collect(),
+        ),
+        _ => Translation::Single(format_segments(
+            &render::segments(pattern, units),
+            args,
+            count,
+        )),
+    }
+}
+
+fn write_context(out: &mut String, category: &Category, target: &TranslationUnitMap) {
+    let base = category.base_strings();
+
+    out.push_str(&format!(
+        "<context>\n    <name>{}</name>\n",
+        encode_text(&category.key.to_string())
+    ));
+
+    for (key, attr) in category.ordered_tu_identity_keys() {
+        // Terms only exist to be inlined into messages
+        if base[key].is_term() {
+            continue;
+        }
+
+        let Some(unit) = base.get(key) else {
+            continue;
+        };
+        let Some(source) = unit.value(attr) else {
+            continue;
+        };
+        if attr.is_none() && source.is_empty() && !unit.attributes.is_empty() {
+            continue;
+        }
+
+        let select = PluralSelect::from_pattern(source);
+        let count = select.as_ref().map(|x| x.variable);
+        let mut args = printf::arguments(base, key, attr);
+        args.retain(|x| Some(x.as_str()) != count);
+
+        // The source of a numerus message is its plural form
+        let source = match select.as_ref() {
+            Some(select) => render::segments(&select.expand("other"), base),
+            None => render::segments(source, base),
+        };
+        let source = format_segments(&source, &mut args, count);
+
+        out.push_str(&format!(
+            "    <message id=\"{}\"{}>\n",
+            encode_double_quoted_attribute(&xliff::unit_id(key, attr)),
+            if count.is_some() {
+                " numerus=\"yes\""
+            } else {
+                ""
+            }
+        ));
+        out.push_str(&format!(
+            "        <source>{}</source>\n",
+            encode_text(&source)
+        ));
+        if let Some(description) = category.descriptions.get(key) {
+            out.push_str(&format!(
+                "        <comment>{}</comment>\n",
+                encode_text(description)
+            ));
+        }
+
+        let value = target.get(key).and_then(|x| x.value(attr));
+        match value {
+            Some(value) => match translation(value, target, &mut args, count) {
+                Translation::Single(x) if count.is_some() => {
+                    out.push_str("        <translation>\n");
+                    for _ in plural::gettext_plurals(&target.locale).categories {
+                        out.push_str(&format!(
+                            "            <numerusform>{}</numerusform>\n",
+                            encode_text(&x)
+                        ));
+                    }
+                    out.push_str("        </translation>\n");
+                }
+                Translation::Single(x) => out.push_str(&format!(
+                    "        <translation>{}</translation>\n",
+                    encode_text(&x)
+                )),
+                Translation::Numerus(forms) => {
+                    out.push_str("        <translation>\n");
+                    for form in forms {
+                        out.push_str(&format!(
+                            "            <numerusform>{}</numerusform>\n",
+                            encode_text(&form)
+                        ));
+                    }
+                    out.push_str("        </translation>\n");
+                }
+            },
+            None => out.push_str("        <translation type=\"unfinished\"></translation>\n"),
+        }
+
+        out.push_str("    </message>\n");
+    }
+
+    out.push_str("</context>\n");
+}
+
+pub fn generate(input: Project) -> Result<PathNode, ParserError> {
+    let mut locales: BTreeMap<LanguageIdentifier, String> = BTreeMap::new();
+
+    for category in input.categories.values() {
+        for locale in category.ordered_locale_keys() {
+            let target = category.get(locale).unwrap();
+            let out = locales.entry(locale.clone()).or_insert_with(|| {
+                format!(
+                    concat!(
+                        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n",
+                        "<!DOCTYPE TS>\n",
+                        "<TS version=\"2.1\" language=\"{}\" sourcelanguage=\"{}\">\n",
+                    ),
+                    qt_locale(locale),
+                    qt_locale(&category.default_locale)
+                )
+            });
+            write_context(out, category, target);
+        }
+    }
+
+    Ok(PathNode::Directory(
+        locales
+            .into_iter()
+            .map(|(locale, mut out)| {
+                out.push_str("</TS>\n");
+                (
+                    format!("{}.ts", qt_locale(&locale)),
+                    PathNode::File(out.into_bytes()),
+                )
+            })
+            .collect(),
+    ))
+}
+
+/// Converts text with numbered placeables into a pattern, mapping `%1` to
+/// the first of `args` and `%n` to `count`. Placeables the base string
+/// doesn't have are an error.
+fn to_pattern(value: &str, args: &[String], count: Option<&str>) -> Result<Pattern, String> {
+    let mut out = Pattern::default();
+    let mut rest = value;
+
+    while let Some(start) = rest.find('%') {
+        out.push_text(&rest[..start]);
+        let tail = rest[start + 1..]
+            .strip_prefix('L')
+            .unwrap_or(&rest[start + 1..]);
+        let digits = tail.len() - tail.trim_start_matches(|x: char| x.is_ascii_digit()).len();
+
+        if digits > 0 {
+            let arg = tail[..digits]
+                .parse::<usize>()
+                .ok()
+                .and_then(|x| args.get(x.checked_sub(1)?))
+                .ok_or_else(|| format!("%{} is not an argument", &tail[..digits]))?;
+            out.push_variable(arg);
+            rest = &tail[digits..];
+        } else if let Some(tail) = tail.strip_prefix('n') {
+            out.push_variable(count.ok_or("%n is only valid in numerus messages")?);
+            rest = tail;
+        } else {
+            out.push_text("%");
+            rest = &rest[start + 1..];
+        }
+    }
+
+    out.push_text(rest);
+    Ok(out)
+}
+
+fn attribute(e: &BytesStart, name: &str) -> anyhow::Result<Option<String>> {
+    Ok(match e.try_get_attribute(name)? {
+        Some(x) => Some(x.unescape_value()?.to_string()),
+        None => None,
+    })
+}
+
+#[derive(Debug, Default)]
+struct ParsedMessage {
+    id: Option<String>,
+    numerus: bool,
+    source: String,
+    comment: Option<String>,
+    /// `None` if the translation is unfinished or obsolete
+    translation: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default)]
+pub struct ParsedFile {
+    language: Option<LanguageIdentifier>,
+    source_language: Option<LanguageIdentifier>,
+    contexts: Vec<(String, Vec<ParsedMessage>)>,
+}
+
+fn language(e: &BytesStart, name: &str) -> anyhow::Result<Option<LanguageIdentifier>> {
+    Ok(match attribute(e, name)? {
+        Some(x) => Some(LanguageIdentifier::from_str(&x.replace('_', "-"))?),
+        None => None,
+    })
+}
+
+pub fn parse_file(xml: &str) -> anyhow::Result<ParsedFile> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut file = ParsedFile::default();
+
+    let mut message: Option<ParsedMessage> = None;
+    let mut reading: Option<&'static str> = None;
+    let mut text = String::new();
+
+    loop {
+        let event = reader.read_event()?;
+        match &event {
+            Event::Eof => break,
+            Event::Start(e) | Event::Empty(e) => {
+                let is_empty = matches!(event, Event::Empty(_));
+                match e.local_name().as_ref() {
+                    b"TS" => {
+                        file.language = language(e, "language")?;
+                        file.source_language = language(e, "sourcelanguage")?;
+                    }
+                    b"context" => file.contexts.push((String::new(), vec![])),
+                    b"message" => {
+                        message = Some(ParsedMessage {
+                            id: attribute(e, "id")?,
+                            numerus: attribute(e, "numerus")?.as_deref() == Some("yes"),
+                            ..Default::default()
+                        });
+                    }
+                    b"translation" => {
+                        let finished = attribute(e, "type")?.is_none();
+                        if let Some(message) = message.as_mut().filter(|_| finished) {
+                            message.translation = Some(vec![]);
+                            if is_empty {
+                                message.translation.as_mut().unwrap().push(String::new());
+                            }
+                        }
+                        reading = Some("translation");
+                        text.clear();
+                    }
+                    b"name" | b"source" | b"comment" | b"numerusform" if !is_empty => {
+                        reading = Some(match e.local_name().as_ref() {
+                            b"name" => "name",
+                            b"source" => "source",
+                            b"comment" => "comment",
+                            _ => "numerusform",
+                        });
+                        text.clear();
+                    }
+                    _ => {}
+                }
+            }
+            Event::Text(e) if reading.is_some() => text.push_str(&e.unescape()?),
+            Event::End(e) => match e.local_name().as_ref() {
+                b"name" => {
+                    if let Some((name, _)) = file.contexts.last_mut() {
+                        *name = std::mem::take(&mut text);
+                    }
+                    reading = None;
+                }
+                b"source" => {
+                    if let Some(message) = message.as_mut() {
+                        message.source = std::mem::take(&mut text);
+                    }
+                    reading = None;
+                }
+                b"comment" => {
+                    if let Some(message) = message.as_mut() {
+                        message.comment = Some(std::mem::take(&mut text));
+                    }
+                    reading = None;
+                }
+                b"numerusform" => {
+                    if let Some(forms) = message.as_mut().and_then(|x| x.translation.as_mut()) {
+                        forms.push(std::mem::take(&mut text));
+                    }
+                    reading = Some("translation");
+                }
+                b"translation" => {
+                    if let Some(forms) = message.as_mut().and_then(|x| x.translation.as_mut()) {
+                        if forms.is_empty() {
+                            forms.push(std::mem::take(&mut text));
+                        }
+                    }
+                    reading = None;
+                }
+                b"message" => {
+                    if let (Some(message), Some((_, messages))) =
+                        (message.take(), file.contexts.last_mut())
+                    {
+                        messages.push(message);
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    Ok(file)
+}
+
+/// The pattern of a translation, rebuilding the plural select of numerus
+/// messages from the language's plural forms.
+fn translation_pattern(
+    forms: &[String],
+    locale: &LanguageIdentifier,
+    args: &[String],
+    count: Option<&str>,
+) -> Result<Pattern, String> {
+    let Some(count) = count.filter(|_| forms.len() > 1) else {
+        return to_pattern(
+            forms.first().map(|x| x.as_str()).unwrap_or_default(),
+            args,
+            count,
+        );
+    };
+
+    let variants = plural::gettext_plurals(locale)
+        .categories
+        .iter()
+        .zip(forms.iter())
+        .map(|(category, value)| Ok((category.to_string(), to_pattern(value, args, Some(count))?)))
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(plural::select(count, variants))
+}
+
+/// Merges the translations of every `.ts` file below the given directory
+/// into the project the files were generated from. Messages are matched by
+/// context and id; the base strings and descriptions of the project are left
+/// as they are.
+pub fn merge_translations(
+    project: &mut Project,
+    path: &Path,
+    keys: &mut Keys,
+) -> anyhow::Result<()> {
+    let files = walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .map(|x| x.into_path())
+        .filter(|x| x.extension().and_then(|x| x.to_str()) == Some("ts"));
+
+    for file in files {
+        let parsed = parse_file(&std::fs::read_to_string(&file)?)?;
+        let Some(locale) = parsed.language.clone() else {
+            eprintln!("[{}] No language; skipping", file.display());
+            continue;
+        };
+
+        for (context, messages) in parsed.contexts {
+            let category_id = keys.category(&context)?;
+            let Some(category) = project.get_mut(&category_id) else {
+                eprintln!(
+                    "[{}] {} is not in the project; skipping",
+                    locale, category_id
+                );
+                continue;
+            };
+            // The file of the source language holds the base strings as they
+            // were written out, with references already inlined
+            if locale == category.default_locale {
+                continue;
+            }
+
+            for message in messages {
+                let Some(id) = message.id.as_deref() else {
+                    eprintln!(
+                        "[{}] Message without an id: {:?}; skipping",
+                        file.display(),
+                        message.source
+                    );
+                    continue;
+                };
+                let Some(forms) = message
+                    .translation
+                    .filter(|x| x.iter().any(|x| !x.is_empty()))
+                else {
+                    continue;
+                };
+
+                let mut chunks = id.splitn(2, "__");
+                let key = keys.unit(&category_id, chunks.next().unwrap())?;
+                let attr = chunks
+                    .next()
+                    .map(|x| keys.attribute(&category_id, &key, x))
+                    .transpose()?;
+
+                // Placeables are numbered the way the base string was
+                // written out
+                let base = category.base_strings();
+                let Some(source) = base.get(&key).and_then(|x| x.value(attr.as_ref())) else {
+                    eprintln!("[{}] {} is not in the base strings; skipping", locale, id);
+                    continue;
+                };
+                let select = PluralSelect::from_pattern(source);
+                let count = select
+                    .as_ref()
+                    .filter(|_| message.numerus)
+                    .map(|x| x.variable.to_string());
+                let mut args = printf::arguments(base, &key, attr.as_ref());
+                args.retain(|x| Some(x) != count.as_ref());
+
+                let value = match translation_pattern(&forms, &locale, &args, count.as_deref()) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        eprintln!("[{}] Could not read {}: {}; skipping", locale, id, e);
+                        continue;
+                    }
+                };
+
+                let unit = category
+                    .entry(locale.clone())
+                    .or_insert_with(|| TranslationUnitMap::new(locale.clone()))
+                    .entry(key.clone())
+                    .or_insert_with(|| TranslationUnit::new(key));
+                match attr {
+                    Some(attr) => {
+                        unit.attributes.insert(attr, value);
+                    }
+                    None => unit.main = value,
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        flt::{parse_pattern, pattern_source},
+        ir::TUIdentifier,
+    };
+    use icu::locid::locale;
+
+    #[test]
+    fn parse_numerus_message() {
+        let file = parse_file(concat!(
+            "<TS version=\"2.1\" language=\"de_DE\" sourcelanguage=\"en\">\n",
+            "<context>\n",
+            "    <name>core</name>\n",
+            "    <message id=\"files\" numerus=\"yes\">\n",
+            "        <source>%n files from %1</source>\n",
+            "        <comment>Shown in the sidebar</comment>\n",
+            "        <translation>\n",
+            "            <numerusform>Eine Datei von %1</numerusform>\n",
+            "            <numerusform>%Ln Dateien von %1</numerusform>\n",
+            "        </translation>\n",
+            "    </message>\n",
+            "    <message id=\"plain\">\n",
+            "        <source>100% done</source>\n",
+            "        <translation type=\"unfinished\"></translation>\n",
+            "    </message>\n",
+            "</context>\n",
+            "</TS>\n",
+        ))
+        .unwrap();
+
+        let locale = file.language.unwrap();
+        assert_eq!(locale.to_string(), "de-DE");
+
+        let (name, messages) = &file.contexts[0];
+        assert_eq!(name, "core");
+        assert_eq!(messages[0].comment.as_deref(), Some("Shown in the sidebar"));
+        assert_eq!(
+            pattern_source(
+                &translation_pattern(
+                    messages[0].translation.as_ref().unwrap(),
+                    &locale,
+                    &["user".to_string()],
+                    Some("count"),
+                )
+                .unwrap()
+            ),
+            concat!(
+                "{ $count ->\n",
+                "    [one] Eine Datei von { $user }\n",
+                "   *[other] { $count } Dateien von { $user }\n",
+                "}",
+            )
+        );
+        assert_eq!(
+            to_pattern(&messages[1].source, &[], None),
+            Ok(Pattern::text("100% done"))
+        );
+        assert!(messages[1].translation.is_none());
+        assert!(to_pattern("%2 von %1", &["user".to_string()], None).is_err());
+    }
+
+    #[test]
+    fn numerus_round_trip() {
+        let key = TUIdentifier::try_from("files").unwrap();
+        let pattern = parse_pattern(concat!(
+            "{ $count ->\n",
+            "    [one] { $user } shared a file\n",
+            "   *[other] { $user } shared { $count } files\n",
+            "}",
+        ))
+        .unwrap();
+        let mut units = TranslationUnitMap::new(locale!("en").id);
+        let mut unit = TranslationUnit::new(key.clone());
+        unit.main = pattern.clone();
+        units.insert(key.clone(), unit);
+
+        let mut args = printf::arguments(&units, &key, None);
+        args.retain(|x| x != "count");
+        let Translation::Numerus(forms) = translation(&pattern, &units, &mut args, Some("count"))
+        else {
+            unreachable!()
+        };
+        assert_eq!(forms, vec!["%1 shared a file", "%1 shared %n files"]);
+        assert_eq!(
+            translation_pattern(&forms, &units.locale, &args, Some("count")),
+            Ok(pattern)
+        );
+    }
+}
@@ -4,27 +4,57 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use calamine::Xlsx;
+use calamine::{Ods, Xlsx};
 use clap::{builder::PossibleValue, Parser, ValueEnum};
 use icu::locid::LanguageIdentifier;
-use stringly::{flt::load_project_from_path, ir::Project, translate};
+use stringly::{
+    flt::load_project_from_path,
+    ir::{Keys, Project},
+    translate,
+};
 
 #[derive(Debug, Clone, Copy)]
 enum FromFormat {
+    Android,
+    Csv,
     Fluent,
+    Ir,
+    Linguist,
+    Ods,
+    Po,
+    Properties,
+    Rails,
+    Xcstrings,
+    Xliff,
     Xlsx,
 }
 
 impl FromFormat {
     pub fn file_ext(&self) -> &str {
         match self {
+            FromFormat::Android => "xml",
+            FromFormat::Csv => "csv",
             FromFormat::Fluent => "ftl",
+            FromFormat::Ir => "json",
+            FromFormat::Linguist => "ts",
+            FromFormat::Ods => "ods",
+            FromFormat::Po => "po",
+            FromFormat::Properties => "properties",
+            FromFormat::Rails => "yml",
+            FromFormat::Xcstrings => "xcstrings",
+            FromFormat::Xliff => "xlf",
             FromFormat::Xlsx => "xlsx",
         }
     }
 
     pub fn validate(&self, path: &Path) -> anyhow::Result<()> {
         match self {
+            FromFormat::Android => {
+                stringly::android::parse_resources(&std::fs::read_to_string(path)?)?;
+            }
+            FromFormat::Csv => {
+                stringly::csv::read_rows(path)?;
+            }
             FromFormat::Fluent => match stringly::flt::parse_flt(path) {
                 Ok(_) => {}
                 Err((_, errs)) => match errs.into_iter().next() {
@@ -32,7 +62,35 @@ impl FromFormat {
                     None => return Err(anyhow::anyhow!("Unknown error")).into(),
                 },
             },
-            FromFormat::Xlsx => todo!(),
+            FromFormat::Ir => {
+                stringly::ir::load_project_from_path(path)?;
+            }
+            FromFormat::Linguist => {
+                stringly::linguist::parse_file(&std::fs::read_to_string(path)?)?;
+            }
+            FromFormat::Po => {
+                stringly::po::parse_po(&std::fs::read_to_string(path)?)?;
+            }
+            FromFormat::Properties => {
+                stringly::properties::parse_properties(&std::fs::read_to_string(path)?)?;
+            }
+            FromFormat::Rails => {
+                serde_yaml::from_str::<serde_yaml::Value>(&std::fs::read_to_string(path)?)?;
+            }
+            FromFormat::Xcstrings => {
+                stringly::xcstrings::load_catalog(path, &mut Keys::strict())?;
+            }
+            FromFormat::Xliff => {
+                stringly::xliff::parse_file(&std::fs::read_to_string(path)?)?;
+            }
+            FromFormat::Ods => {
+                let ods: Ods<_> = calamine::open_workbook(path)?;
+                stringly::xlsx::parse_workbook(ods, &mut Keys::strict())?;
+            }
+            FromFormat::Xlsx => {
+                let xlsx: Xlsx<_> = calamine::open_workbook(path)?;
+                stringly::xlsx::parse_workbook(xlsx, &mut Keys::strict())?;
+            }
         }
 
         Ok(())
@@ -42,7 +100,17 @@ impl FromFormat {
 impl Display for FromFormat {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(match self {
+            FromFormat::Android => "Android",
+            FromFormat::Csv => "CSV",
             FromFormat::Fluent => "Fluent",
+            FromFormat::Ir => "stringly JSON",
+            FromFormat::Linguist => "Qt Linguist",
+            FromFormat::Ods => "ODS",
+            FromFormat::Po => "gettext PO",
+            FromFormat::Properties => "Java properties",
+            FromFormat::Rails => "Rails YAML",
+            FromFormat::Xcstrings => "String Catalog",
+            FromFormat::Xliff => "XLIFF",
             FromFormat::Xlsx => "XLSX",
         })
     }
@@ -50,11 +118,34 @@ impl Display for FromFormat {
 
 impl ValueEnum for FromFormat {
     fn value_variants<'a>() -> &'a [Self] {
-        &[Self::Fluent, Self::Xlsx]
+        &[
+            Self::Android,
+            Self::Csv,
+            Self::Fluent,
+            Self::Ir,
+            Self::Linguist,
+            Self::Ods,
+            Self::Po,
+            Self::Properties,
+            Self::Rails,
+            Self::Xcstrings,
+            Self::Xliff,
+            Self::Xlsx,
+        ]
     }
 
     fn to_possible_value(&self) -> Option<PossibleValue> {
         match self {
+            Self::Android => Some(PossibleValue::new("android")),
+            Self::Csv => Some(PossibleValue::new("csv").alias("tsv")),
+            Self::Ir => Some(PossibleValue::new("ir").alias("json")),
+            Self::Linguist => Some(PossibleValue::new("linguist").alias("qt")),
+            Self::Ods => Some(PossibleValue::new("ods")),
+            Self::Po => Some(PossibleValue::new("po").alias("gettext")),
+            Self::Properties => Some(PossibleValue::new("properties")),
+            Self::Rails => Some(PossibleValue::new("rails").alias("yaml").alias("yml")),
+            Self::Xcstrings => Some(PossibleValue::new("xcstrings")),
+            Self::Xliff => Some(PossibleValue::new("xliff").alias("xlf")),
             Self::Xlsx => Some(PossibleValue::new("xlsx")),
             Self::Fluent => Some(PossibleValue::new("fluent").alias("ftl").alias("flt")),
         }
@@ -63,16 +154,58 @@ impl ValueEnum for FromFormat {
 
 #[derive(Debug, Clone, Copy)]
 enum Target {
+    Android,
+    AppleStrings,
+    Arb,
+    ArbDart,
+    Csv,
     Fluent,
+    Ir,
+    Json,
+    Kotlin,
+    Linguist,
+    MessageFormat,
+    Ods,
+    Po,
+    Properties,
+    Rails,
+    Resx,
+    Rust,
+    Swift,
+    Tmx,
+    Tsv,
     TypeScript,
+    Xcstrings,
+    Xliff,
     Xlsx,
 }
 
 impl Display for Target {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str(match self {
+            Target::Android => "Android",
+            Target::AppleStrings => "Apple Strings",
+            Target::Arb => "Flutter ARB",
+            Target::ArbDart => "Flutter ARB with Dart",
+            Target::Csv => "CSV",
             Target::Fluent => "Fluent",
+            Target::Ir => "stringly JSON",
+            Target::Json => "i18next JSON",
+            Target::Kotlin => "Kotlin",
+            Target::Linguist => "Qt Linguist",
+            Target::MessageFormat => "ICU MessageFormat",
+            Target::Ods => "ODS",
+            Target::Po => "gettext PO",
+            Target::Properties => "Java properties",
+            Target::Rails => "Rails YAML",
+            Target::Resx => ".NET resx",
+            Target::Rust => "Rust",
+            Target::Swift => "Swift",
+            Target::Tmx => "TMX",
             Target::TypeScript => "TypeScript",
+            Target::Tsv => "TSV",
+            Target::Xcstrings => "String Catalog",
+            Target::Xliff => "XLIFF",
             Target::Xlsx => "XLSX",
         })
     }
@@ -80,13 +213,63 @@ impl Display for Target {
 
 impl ValueEnum for Target {
     fn value_variants<'a>() -> &'a [Self] {
-        &[Self::Fluent, Self::TypeScript, Self::Xlsx]
+        &[
+            Self::Android,
+            Self::AppleStrings,
+            Self::Arb,
+            Self::ArbDart,
+            Self::Csv,
+            Self::Fluent,
+            Self::Ir,
+            Self::Json,
+            Self::Kotlin,
+            Self::Linguist,
+            Self::MessageFormat,
+            Self::Ods,
+            Self::Po,
+            Self::Properties,
+            Self::Rails,
+            Self::Resx,
+            Self::Rust,
+            Self::Swift,
+            Self::Tmx,
+            Self::Tsv,
+            Self::TypeScript,
+            Self::Xcstrings,
+            Self::Xliff,
+            Self::Xlsx,
+        ]
     }
 
     fn to_possible_value(&self) -> Option<PossibleValue> {
         match self {
+            Target::Android => Some(PossibleValue::new("android")),
+            Target::AppleStrings => Some(PossibleValue::new("apple-strings").alias("strings")),
+            Target::Arb => Some(PossibleValue::new("arb")),
+            Target::ArbDart => Some(PossibleValue::new("arb-dart").alias("flutter")),
+            Target::Csv => Some(PossibleValue::new("csv")),
+            Target::Rust => Some(PossibleValue::new("rust").alias("rs")),
+            Target::Swift => Some(PossibleValue::new("swift")),
+            Target::Tmx => Some(PossibleValue::new("tmx")),
+            Target::Tsv => Some(PossibleValue::new("tsv")),
             Target::TypeScript => Some(PossibleValue::new("typescript").alias("ts")),
             Target::Fluent => Some(PossibleValue::new("fluent").alias("ftl").alias("flt")),
+            Target::Ir => Some(PossibleValue::new("ir")),
+            Target::Json => Some(PossibleValue::new("json").alias("i18next")),
+            Target::Kotlin => Some(PossibleValue::new("kotlin").alias("kt")),
+            Target::Linguist => Some(PossibleValue::new("linguist").alias("qt")),
+            Target::MessageFormat => Some(
+                PossibleValue::new("formatjs")
+                    .alias("icu")
+                    .alias("messageformat"),
+            ),
+            Target::Ods => Some(PossibleValue::new("ods")),
+            Target::Po => Some(PossibleValue::new("po").alias("gettext")),
+            Target::Properties => Some(PossibleValue::new("properties")),
+            Target::Rails => Some(PossibleValue::new("rails").alias("yaml").alias("yml")),
+            Target::Resx => Some(PossibleValue::new("resx")),
+            Target::Xcstrings => Some(PossibleValue::new("xcstrings")),
+            Target::Xliff => Some(PossibleValue::new("xliff").alias("xlf")),
             Target::Xlsx => Some(PossibleValue::new("xlsx")),
         }
     }
@@ -135,6 +318,19 @@ struct GenerateArgs {
     #[arg(short, long)]
     /// Path to the output directory
     output_path: PathBuf,
+
+    #[arg(long)]
+    /// Rewrite keys that are not valid identifiers instead of failing
+    sanitize_keys: bool,
+
+    #[arg(long)]
+    /// The Fluent project that XLIFF or Linguist files were generated from,
+    /// which their translations are merged into
+    base_path: Option<PathBuf>,
+
+    #[arg(long, default_value = "en")]
+    /// The app's default locale, for Rails locale files which don't record it
+    default_locale: LanguageIdentifier,
 }
 
 #[derive(Debug, Parser)]
@@ -158,6 +354,19 @@ struct TranslateArgs {
     /// The target language to be translated into
     target_language: LanguageIdentifier,
 
+    #[arg(long)]
+    /// Rewrite keys that are not valid identifiers instead of failing
+    sanitize_keys: bool,
+
+    #[arg(long)]
+    /// The Fluent project that XLIFF or Linguist files were generated from,
+    /// which their translations are merged into
+    base_path: Option<PathBuf>,
+
+    #[arg(long, default_value = "en")]
+    /// The app's default locale, for Rails locale files which don't record it
+    default_locale: LanguageIdentifier,
+
     #[arg(env = "GOOGLE_API_KEY", long = "api-key")]
     /// Google API key
     google_api_key: String,
@@ -168,18 +377,105 @@ async fn main() -> anyhow::Result<()> {
     run().await
 }
 
-fn load_project(from_format: FromFormat, input_path: &Path) -> anyhow::Result<Project> {
-    Ok(match from_format {
-        FromFormat::Fluent => load_project_from_path(input_path)?,
+fn load_project(
+    from_format: FromFormat,
+    input_path: &Path,
+    base_path: Option<&Path>,
+    default_locale: &LanguageIdentifier,
+    sanitize_keys: bool,
+) -> anyhow::Result<Project> {
+    let mut keys = if sanitize_keys {
+        Keys::sanitizing()
+    } else {
+        Keys::strict()
+    };
+    let keys = &mut keys;
+
+    let project = match from_format {
+        FromFormat::Android => stringly::android::load_project_from_path(input_path, keys)?,
+        FromFormat::Csv => stringly::csv::load_project_from_path(input_path, keys)?,
+        FromFormat::Fluent => load_project_from_path(input_path, keys)?,
+        FromFormat::Ir => stringly::ir::load_project_from_path(input_path)?,
+        FromFormat::Linguist => {
+            let Some(base_path) = base_path else {
+                anyhow::bail!(
+                    "Linguist files are merged into a Fluent project; pass it with --base-path"
+                );
+            };
+            let mut project = load_project_from_path(base_path, keys)?;
+            stringly::linguist::merge_translations(&mut project, input_path, keys)?;
+            project
+        }
+        FromFormat::Ods => {
+            let ods: Ods<_> = calamine::open_workbook(input_path)?;
+            stringly::xlsx::parse_workbook(ods, keys)?
+        }
+        FromFormat::Po => stringly::po::load_project_from_path(input_path, keys)?,
+        FromFormat::Properties => stringly::properties::load_project_from_path(input_path, keys)?,
+        FromFormat::Rails => {
+            stringly::rails::load_project_from_path(input_path, default_locale, keys)?
+        }
+        FromFormat::Xcstrings => stringly::xcstrings::load_project_from_path(input_path, keys)?,
+        FromFormat::Xliff => {
+            let Some(base_path) = base_path else {
+                anyhow::bail!(
+                    "XLIFF files are merged into a Fluent project; pass it with --base-path"
+                );
+            };
+            let mut project = load_project_from_path(base_path, keys)?;
+            stringly::xliff::merge_targets(&mut project, input_path, keys)?;
+            project
+        }
         FromFormat::Xlsx => {
             let xlsx: Xlsx<_> = calamine::open_workbook(input_path)?;
-            Project::try_from(xlsx)?
+            stringly::xlsx::parse_workbook(xlsx, keys)?
         }
-    })
+    };
+
+    for (original, sanitized) in keys.renamed() {
+        eprintln!("Renamed {original:?} to {sanitized}");
+    }
+
+    Ok(project)
 }
 
 fn generate(to_format: Target, project: Project, output_path: &Path) -> anyhow::Result<()> {
     let tree = match to_format {
+        Target::Android => match stringly::android::generate(project) {
+            Ok(v) => v,
+            Err(error) => {
+                eprintln!("{:?}", error);
+                return Err(error.into());
+            }
+        },
+        Target::AppleStrings => match stringly::apple::generate(project) {
+            Ok(v) => v,
+            Err(error) => {
+                eprintln!("{:?}", error);
+                return Err(error.into());
+            }
+        },
+        Target::Arb => match stringly::arb::generate(project) {
+            Ok(v) => v,
+            Err(error) => {
+                eprintln!("{}", error);
+                return Err(error.into());
+            }
+        },
+        Target::ArbDart => match stringly::arb::generate_with_dart(project) {
+            Ok(v) => v,
+            Err(error) => {
+                eprintln!("{}", error);
+                return Err(error.into());
+            }
+        },
+        Target::Csv => match stringly::csv::generate(project) {
+            Ok(v) => v,
+            Err(error) => {
+                eprintln!("{:?}", error);
+                return Err(error.into());
+            }
+        },
         Target::Fluent => match stringly::flt::generate(project) {
             Ok(v) => v,
             Err(error) => {
@@ -187,6 +483,104 @@ fn generate(to_format: Target, project: Project, output_path: &Path) -> anyhow::
                 return Err(error.into());
             }
         },
+        Target::Ir => match stringly::ir::generate(project) {
+            Ok(v) => v,
+            Err(error) => {
+                eprintln!("{:?}", error);
+                return Err(error.into());
+            }
+        },
+        Target::Json => match stringly::i18next::generate(project) {
+            Ok(v) => v,
+            Err(error) => {
+                eprintln!("{:?}", error);
+                return Err(error.into());
+            }
+        },
+        Target::Kotlin => match stringly::kotlin::generate(project) {
+            Ok(v) => v,
+            Err(error) => {
+                eprintln!("{:?}", error);
+                return Err(error.into());
+            }
+        },
+        Target::Linguist => match stringly::linguist::generate(project) {
+            Ok(v) => v,
+            Err(error) => {
+                eprintln!("{:?}", error);
+                return Err(error.into());
+            }
+        },
+        Target::MessageFormat => match stringly::messageformat::generate(project) {
+            Ok(v) => v,
+            Err(error) => {
+                eprintln!("{}", error);
+                return Err(error.into());
+            }
+        },
+        Target::Ods => match stringly::ods::generate(project) {
+            Ok(v) => v,
+            Err(error) => {
+                eprintln!("{:?}", error);
+                return Err(error.into());
+            }
+        },
+        Target::Po => match stringly::po::generate(project) {
+            Ok(v) => v,
+            Err(error) => {
+                eprintln!("{:?}", error);
+                return Err(error.into());
+            }
+        },
+        Target::Properties => match stringly::properties::generate(project) {
+            Ok(v) => v,
+            Err(error) => {
+                eprintln!("{:?}", error);
+                return Err(error.into());
+            }
+        },
+        Target::Rails => match stringly::rails::generate(project) {
+            Ok(v) => v,
+            Err(error) => {
+                eprintln!("{:?}", error);
+                return Err(error.into());
+            }
+        },
+        Target::Resx => match stringly::resx::generate(project) {
+            Ok(v) => v,
+            Err(error) => {
+                eprintln!("{:?}", error);
+                return Err(error.into());
+            }
+        },
+        Target::Rust => match stringly::rust::generate(project) {
+            Ok(v) => v,
+            Err(error) => {
+                eprintln!("{:?}", error);
+                return Err(error.into());
+            }
+        },
+        Target::Swift => match stringly::swift::generate(project) {
+            Ok(v) => v,
+            Err(error) => {
+                eprintln!("{:?}", error);
+                return Err(error.into());
+            }
+        },
+        Target::Tmx => match stringly::tmx::generate(project) {
+            Ok(v) => v,
+            Err(error) => {
+                eprintln!("{:?}", error);
+                return Err(error.into());
+            }
+        },
+        Target::Tsv => match stringly::csv::generate_tsv(project) {
+            Ok(v) => v,
+            Err(error) => {
+                eprintln!("{:?}", error);
+                return Err(error.into());
+            }
+        },
         Target::TypeScript => match stringly::ts::generate(project) {
             Ok(v) => v,
             Err(error) => {
@@ -194,6 +588,20 @@ fn generate(to_format: Target, project: Project, output_path: &Path) -> anyhow::
                 return Err(error.into());
             }
         },
+        Target::Xcstrings => match stringly::xcstrings::generate(project) {
+            Ok(v) => v,
+            Err(error) => {
+                eprintln!("{:?}", error);
+                return Err(error.into());
+            }
+        },
+        Target::Xliff => match stringly::xliff::generate(project) {
+            Ok(v) => v,
+            Err(error) => {
+                eprintln!("{:?}", error);
+                return Err(error.into());
+            }
+        },
         Target::Xlsx => match stringly::xlsx::generate(project) {
             Ok(v) => v,
             Err(error) => {
@@ -218,7 +626,13 @@ async fn run() -> anyhow::Result<()> {
     match command {
         Command::Generate(args) => {
             eprintln!("Loading from format: {}", args.from_format);
-            let project = load_project(args.from_format, &args.input_path)?;
+            let project = load_project(
+                args.from_format,
+                &args.input_path,
+                args.base_path.as_deref(),
+                &args.default_locale,
+                args.sanitize_keys,
+            )?;
 
             eprintln!("Generating for format: {}", args.to_format);
             generate(args.to_format, project, &args.output_path)?;
@@ -226,7 +640,13 @@ async fn run() -> anyhow::Result<()> {
         }
         Command::Translate(args) => {
             eprintln!("Loading from format: {}", args.from_format);
-            let project = load_project(args.from_format, &args.input_path)?;
+            let project = load_project(
+                args.from_format,
+                &args.input_path,
+                args.base_path.as_deref(),
+                &args.default_locale,
+                args.sanitize_keys,
+            )?;
             let project =
                 translate::process(&project, &args.target_language, &args.google_api_key).await?;
             eprintln!("Generating for format: {}", args.to_format);
@@ -0,0 +1,396 @@
+//! ICU MessageFormat, as FormatJS `<locale>.json` message files.
+//!
+//! Patterns are converted from the Fluent AST rather than by substitution:
+//! plural selects become `{count, plural, ...}`, other selects become
+//! `{gender, select, ...}`, and message and term references are inlined.
+//! Anything MessageFormat has no equivalent for is reported as an error
+//! instead of being silently dropped.
+
+use std::{collections::BTreeMap, fmt::Display};
+
+use fluent_syntax::parser::ParserError;
+use serde::Serialize;
+
+use crate::{
+    ir::{
+        Arguments, Element, Expression, Pattern, Project, Select, TUIdentifier, TranslationUnitMap,
+        VariantKey,
+    },
+    plural, PathNode,
+};
+
+/// Guards against cycles between messages referencing each other.
+const MAX_DEPTH: usize = 8;
+
+#[derive(Debug)]
+pub enum MessageFormatError {
+    Parser(ParserError),
+    /// Messages that cannot be expressed in MessageFormat, as
+    /// `(id, locale, reason)`.
+    Unsupported(Vec<(String, String, String)>),
+}
+
+impl Display for MessageFormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MessageFormatError::Parser(e) => e.fmt(f),
+            MessageFormatError::Unsupported(messages) => {
+                writeln!(f, "{} message(s) cannot be expressed:", messages.len())?;
+                for (id, locale, reason) in messages {
+                    writeln!(f, "  [{locale}] {id}: {reason}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for MessageFormatError {}
+
+impl From<ParserError> for MessageFormatError {
+    fn from(value: ParserError) -> Self {
+        MessageFormatError::Parser(value)
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FormatJsMessage {
+    pub default_message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// MessageFormat argument names cannot contain pattern syntax such as `-`.
+pub fn argument_name(name: &str) -> String {
+    name.replace('-', "_")
+}
+
+fn escape(value: &str, in_plural: bool) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\'' => out.push_str("''"),
+            '{' | '}' => out.push_str(&format!("'{c}'")),
+            '#' if in_plural => out.push_str("'#'"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+struct Converter<'a> {
+    units: &'a TranslationUnitMap,
+    depth: usize,
+    /// The variable of the innermost plural, which `#` stands for.
+    plural: Option<String>,
+    /// Whether plural variables are written as `#`.
+    pound: bool,
+}
+
+impl<'a> Converter<'a> {
+    fn pattern(&mut self, pattern: &Pattern) -> Result<String, String> {
+        let mut out = String::new();
+        for element in pattern.elements.iter() {
+            match element {
+                Element::Text(value) => out.push_str(&escape(value, self.plural.is_some())),
+                Element::Placeable(expression) => out.push_str(&self.expression(expression)?),
+                Element::Select(select) => out.push_str(&self.select(select)?),
+            }
+        }
+        Ok(out)
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Result<String, String> {
+        match expression {
+            Expression::String(value) => Ok(escape(value, self.plural.is_some())),
+            Expression::Number(value) => Ok(value.clone()),
+            Expression::Variable(name) => {
+                if self.pound && self.plural.as_deref() == Some(name.as_str()) {
+                    Ok("#".to_string())
+                } else {
+                    Ok(format!("{{{}}}", argument_name(name)))
+                }
+            }
+            Expression::Function { name, arguments } => self.function(name, arguments),
+            Expression::Message { id, attribute } => self.reference(id, attribute.as_deref()),
+            Expression::Term {
+                id,
+                attribute,
+                arguments,
+            } => {
+                if arguments.is_some() {
+                    return Err(format!("term reference -{} has arguments", id));
+                }
+                self.reference(&format!("-{}", id), attribute.as_deref())
+            }
+        }
+    }
+
+    fn function(&mut self, name: &str, arguments: &Arguments) -> Result<String, String> {
+        let variable = match arguments.positional.as_slice() {
+            [Expression::Variable(name)] => name,
+            _ => return Err(format!("{name}() must take a single variable")),
+        };
+        if name == "NUMBER" && arguments.named.is_empty() {
+            if !self.pound {
+                return Ok(format!("{{{}}}", argument_name(variable)));
+            }
+            if self.plural.as_deref() == Some(variable.as_str()) {
+                return Ok("#".to_string());
+            }
+        }
+        let variable = argument_name(variable);
+
+        let option = |key: &str| {
+            arguments
+                .named
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| match value {
+                    Expression::String(value) | Expression::Number(value) => value.clone(),
+                    _ => String::new(),
+                })
+        };
+        let check_options = |allowed: &[&str]| match arguments
+            .named
+            .iter()
+            .find(|(x, _)| !allowed.contains(&x.as_str()))
+        {
+            Some((x, _)) => Err(format!("{name}() option {x} is not supported")),
+            None => Ok(()),
+        };
+
+        match name {
+            "NUMBER" => {
+                check_options(&["style"])?;
+                match option("style").as_deref() {
+                    None | Some("decimal") => Ok(format!("{{{variable}, number}}")),
+                    Some("percent") => Ok(format!("{{{variable}, number, percent}}")),
+                    Some(x) => Err(format!("NUMBER() style {x} is not supported")),
+                }
+            }
+            "DATETIME" => {
+                check_options(&["dateStyle", "timeStyle"])?;
+                match (option("dateStyle"), option("timeStyle")) {
+                    (Some(_), Some(_)) => {
+                        Err("DATETIME() with both dateStyle and timeStyle".to_string())
+                    }
+                    (None, Some(style)) => Ok(format!("{{{variable}, time, {style}}}")),
+                    (Some(style), None) => Ok(format!("{{{variable}, date, {style}}}")),
+                    (None, None) => Ok(format!("{{{variable}, date}}")),
+                }
+            }
+            _ => Err(format!("function {name}() has no MessageFormat equivalent")),
+        }
+    }
+
+    fn reference(&mut self, id: &str, attribute: Option<&str>) -> Result<String, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err(format!("reference to {id} is too deeply nested"));
+        }
+
+        let units = self.units;
+        let pattern = TUIdentifier::try_from(id)
+            .ok()
+            .and_then(|x| units.get(&x))
+            .and_then(|unit| match attribute {
+                Some(attr) => unit.attributes.get(&TUIdentifier::try_from(attr).ok()?),
+                None => Some(&unit.main),
+            })
+            .ok_or_else(|| format!("reference to {id} could not be resolved"))?;
+
+        self.depth += 1;
+        let out = self.pattern(pattern);
+        self.depth -= 1;
+        out
+    }
+
+    fn select(&mut self, select: &Select) -> Result<String, String> {
+        let variants = &select.variants;
+        let variable = match &select.selector {
+            Expression::Variable(name) => name,
+            Expression::Function { name, arguments }
+                if name == "NUMBER" && arguments.named.is_empty() =>
+            {
+                match arguments.positional.as_slice() {
+                    [Expression::Variable(name)] => name,
+                    _ => return Err("NUMBER() selector must take a single variable".to_string()),
+                }
+            }
+            _ => return Err("only variables can be selected on".to_string()),
+        };
+
+        let is_plural = plural::is_plural_select(select);
+
+        let previous = self.plural.clone();
+        if is_plural {
+            self.plural = Some(variable.clone());
+        }
+
+        let mut out = format!(
+            "{{{}, {}, ",
+            argument_name(variable),
+            if is_plural { "plural" } else { "select" }
+        );
+        let mut has_other = false;
+        let mut default = None;
+        for variant in variants.iter() {
+            let key = match &variant.key {
+                VariantKey::Identifier(name) => name.clone(),
+                VariantKey::Number(value) if is_plural => format!("={value}"),
+                VariantKey::Number(value) => value.clone(),
+            };
+            let value = match self.pattern(&variant.value) {
+                Ok(x) => x,
+                Err(e) => {
+                    self.plural = previous;
+                    return Err(e);
+                }
+            };
+            has_other |= key == "other";
+            if variant.default {
+                default = Some(value.clone());
+            }
+            out.push_str(&format!("{key} {{{value}}} "));
+        }
+        self.plural = previous;
+
+        // MessageFormat always falls back to `other`, so the default variant
+        // is repeated under that name if needed.
+        if !has_other {
+            let default = default.ok_or_else(|| "select has no default variant".to_string())?;
+            out.push_str(&format!("other {{{default}}} "));
+        }
+
+        out.pop();
+        out.push('}');
+        Ok(out)
+    }
+}
+
+/// Converts a Fluent pattern into MessageFormat, resolving references
+/// against `units`. Errors describe what could not be expressed.
+pub fn convert(pattern: &Pattern, units: &TranslationUnitMap) -> Result<String, String> {
+    Converter {
+        units,
+        depth: 0,
+        plural: None,
+        pound: true,
+    }
+    .pattern(pattern)
+}
+
+/// Like [`convert`], but writes plural variables as plain arguments instead
+/// of `#`, and leaves number formatting without options to the placeholder,
+/// for parsers such as Flutter's that do not support either.
+pub fn convert_without_pound(
+    pattern: &Pattern,
+    units: &TranslationUnitMap,
+) -> Result<String, String> {
+    Converter {
+        units,
+        depth: 0,
+        plural: None,
+        pound: false,
+    }
+    .pattern(pattern)
+}
+
+fn message_id(category: &str, key: &TUIdentifier, attr: Option<&TUIdentifier>) -> String {
+    match attr {
+        Some(attr) => format!("{}.{}__{}", category, key, attr),
+        None => format!("{}.{}", category, key),
+    }
+}
+
+pub fn generate(input: Project) -> Result<PathNode, MessageFormatError> {
+    let mut locales: BTreeMap<String, BTreeMap<String, FormatJsMessage>> = BTreeMap::new();
+    let mut unsupported = vec![];
+
+    for (category_id, category) in input.categories.iter() {
+        for (key, attr) in category.ordered_tu_identity_keys() {
+            // Terms only exist to be inlined into messages
+            if category.base_strings()[key].is_term() {
+                continue;
+            }
+
+            let id = message_id(category_id, key, attr);
+
+            for (locale, units) in category.translation_units.iter() {
+                let Some(value) = units.get(key).and_then(|x| x.value(attr)) else {
+                    continue;
+                };
+
+                match convert(value, units) {
+                    Ok(message) => {
+                        locales.entry(locale.to_string()).or_default().insert(
+                            id.clone(),
+                            FormatJsMessage {
+                                default_message: message,
+                                description: category.descriptions.get(key).cloned(),
+                            },
+                        );
+                    }
+                    Err(reason) => unsupported.push((id.clone(), locale.to_string(), reason)),
+                }
+            }
+        }
+    }
+
+    if !unsupported.is_empty() {
+        return Err(MessageFormatError::Unsupported(unsupported));
+    }
+
+    Ok(PathNode::Directory(
+        locales
+            .into_iter()
+            .map(|(locale, messages)| {
+                (
+                    format!("{locale}.json"),
+                    PathNode::File(
+                        serde_json::to_string_pretty(&messages)
+                            .unwrap()
+                            .into_bytes(),
+                    ),
+                )
+            })
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flt::parse_pattern;
+    use icu::locid::langid;
+
+    fn convert_source(value: &str) -> Result<String, String> {
+        let units = TranslationUnitMap::new(langid!("en"));
+        convert(&parse_pattern(value).unwrap(), &units)
+    }
+
+    #[test]
+    fn convert_plural_select() {
+        assert_eq!(
+            convert_source(
+                "{ $count ->\n    [0] No files\n    [one] One file\n   *[other] { $count } files for { $user-name }\n}"
+            )
+            .unwrap(),
+            "{count, plural, =0 {No files} one {One file} other {# files for {user_name}}}"
+        );
+    }
+
+    #[test]
+    fn convert_select_without_other() {
+        assert_eq!(
+            convert_source("{ $gender ->\n   *[male] He's {\"{\"}\n    [female] She's\n}").unwrap(),
+            "{gender, select, male {He''s '{'} female {She''s} other {He''s '{'}}"
+        );
+    }
+
+    #[test]
+    fn reject_term_arguments() {
+        assert!(convert_source("{ -brand(case: \"genitive\") }").is_err());
+    }
+}
@@ -0,0 +1,220 @@
+//! OpenDocument spreadsheets (`.ods`) with the same sheets as
+//! [`crate::xlsx`], for translators working in LibreOffice.
+//!
+//! Workbooks are read back through calamine and [`xlsx::parse_sheet`].
+
+use std::io::{Cursor, Write};
+
+use html_escape::{encode_double_quoted_attribute, encode_text};
+use zip::{result::ZipError, write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    ir::{Category, Project},
+    xlsx, PathNode,
+};
+
+const MIMETYPE: &str = "application/vnd.oasis.opendocument.spreadsheet";
+
+const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.2">
+ <manifest:file-entry manifest:full-path="/" manifest:version="1.2" manifest:media-type="application/vnd.oasis.opendocument.spreadsheet"/>
+ <manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>
+ <manifest:file-entry manifest:full-path="settings.xml" manifest:media-type="text/xml"/>
+</manifest:manifest>
+"#;
+
+/// The column width and cell formats of [`xlsx::generate`]'s sheets.
+const STYLES: &str = r#"<office:font-face-decls>
+  <style:font-face style:name="Roboto Mono" svg:font-family="'Roboto Mono'" style:font-pitch="fixed"/>
+ </office:font-face-decls>
+ <office:automatic-styles>
+  <style:style style:name="co1" style:family="table-column">
+   <style:table-column-properties style:column-width="5.69cm"/>
+  </style:style>
+  <style:style style:name="header" style:family="table-cell">
+   <style:table-cell-properties fo:wrap-option="wrap" style:vertical-align="middle"/>
+   <style:text-properties fo:font-size="8pt" fo:font-weight="bold"/>
+  </style:style>
+  <style:style style:name="identifier" style:family="table-cell">
+   <style:table-cell-properties fo:wrap-option="wrap" style:vertical-align="middle"/>
+   <style:text-properties style:font-name="Roboto Mono" fo:font-size="8pt"/>
+  </style:style>
+  <style:style style:name="text" style:family="table-cell">
+   <style:table-cell-properties fo:wrap-option="wrap" style:vertical-align="top"/>
+  </style:style>
+ </office:automatic-styles>
+"#;
+
+/// Writes text as paragraphs, keeping the spaces that ODF would otherwise
+/// collapse or strip: leading, trailing and repeated ones.
+fn write_text(out: &mut String, value: &str) {
+    for line in value.split('\n') {
+        out.push_str("<text:p>");
+        let mut previous_space = true;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                ' ' if previous_space || chars.peek().is_none() => out.push_str("<text:s/>"),
+                '\t' => out.push_str("<text:tab/>"),
+                c => out.push_str(&encode_text(&c.to_string())),
+            }
+            previous_space = c == ' ';
+        }
+        out.push_str("</text:p>");
+    }
+}
+
+fn write_table(out: &mut String, category: &Category) {
+    let rows = xlsx::sheet_rows(category);
+
+    out.push_str(&format!(
+        "   <table:table table:name=\"{}\">\n",
+        encode_double_quoted_attribute(&category.name)
+    ));
+    for col in 0..rows[0].len() {
+        let style = match col {
+            0 => " table:default-cell-style-name=\"identifier\"",
+            1 | 2 => " table:default-cell-style-name=\"text\"",
+            _ => "",
+        };
+        out.push_str(&format!(
+            "    <table:table-column table:style-name=\"co1\"{style}/>\n"
+        ));
+    }
+
+    for (row, cells) in rows.iter().enumerate() {
+        out.push_str("    <table:table-row>");
+        for (col, cell) in cells.iter().enumerate() {
+            let Some(cell) = cell else {
+                out.push_str("<table:table-cell/>");
+                continue;
+            };
+            let style = match (row, col) {
+                (0, _) => "header",
+                (_, 0 | 1) => "identifier",
+                _ => "text",
+            };
+            out.push_str(&format!(
+                "<table:table-cell table:style-name=\"{style}\" office:value-type=\"string\">"
+            ));
+            write_text(out, cell);
+            out.push_str("</table:table-cell>");
+        }
+        out.push_str("</table:table-row>\n");
+    }
+
+    out.push_str("   </table:table>\n");
+}
+
+fn content(categories: &[&Category]) -> String {
+    let mut out = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<office:document-content",
+        " xmlns:office=\"urn:oasis:names:tc:opendocument:xmlns:office:1.0\"",
+        " xmlns:style=\"urn:oasis:names:tc:opendocument:xmlns:style:1.0\"",
+        " xmlns:text=\"urn:oasis:names:tc:opendocument:xmlns:text:1.0\"",
+        " xmlns:table=\"urn:oasis:names:tc:opendocument:xmlns:table:1.0\"",
+        " xmlns:fo=\"urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0\"",
+        " xmlns:svg=\"urn:oasis:names:tc:opendocument:xmlns:svg-compatible:1.0\"",
+        " office:version=\"1.2\">\n ",
+    ));
+    out.push_str(STYLES);
+    out.push_str(" <office:body>\n  <office:spreadsheet>\n");
+    for category in categories {
+        write_table(&mut out, category);
+    }
+    out.push_str("  </office:spreadsheet>\n </office:body>\n</office:document-content>\n");
+    out
+}
+
+/// The view settings freezing the header row and the identifier and
+/// description columns of every sheet.
+fn settings(categories: &[&Category]) -> String {
+    let mut out = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<office:document-settings",
+        " xmlns:office=\"urn:oasis:names:tc:opendocument:xmlns:office:1.0\"",
+        " xmlns:config=\"urn:oasis:names:tc:opendocument:xmlns:config:1.0\"",
+        " office:version=\"1.2\">\n",
+        " <office:settings>\n",
+        "  <config:config-item-set config:name=\"ooo:view-settings\">\n",
+        "   <config:config-item-map-indexed config:name=\"Views\">\n",
+        "    <config:config-item-map-entry>\n",
+        "     <config:config-item config:name=\"ViewId\" config:type=\"string\">view1</config:config-item>\n",
+        "     <config:config-item-map-named config:name=\"Tables\">\n",
+    ));
+
+    for category in categories {
+        out.push_str(&format!(
+            "      <config:config-item-map-entry config:name=\"{}\">\n",
+            encode_double_quoted_attribute(&category.name)
+        ));
+        for (name, kind, value) in [
+            ("HorizontalSplitMode", "short", 2),
+            ("VerticalSplitMode", "short", 2),
+            ("HorizontalSplitPosition", "int", 2),
+            ("VerticalSplitPosition", "int", 1),
+            ("ActiveSplitRange", "short", 2),
+            ("PositionLeft", "int", 0),
+            ("PositionRight", "int", 2),
+            ("PositionTop", "int", 0),
+            ("PositionBottom", "int", 1),
+        ] {
+            out.push_str(&format!(
+                "       <config:config-item config:name=\"{name}\" config:type=\"{kind}\">{value}</config:config-item>\n"
+            ));
+        }
+        out.push_str("      </config:config-item-map-entry>\n");
+    }
+
+    out.push_str(concat!(
+        "     </config:config-item-map-named>\n",
+        "    </config:config-item-map-entry>\n",
+        "   </config:config-item-map-indexed>\n",
+        "  </config:config-item-set>\n",
+        " </office:settings>\n",
+        "</office:document-settings>\n",
+    ));
+    out
+}
+
+pub fn generate(project: Project) -> Result<PathNode, ZipError> {
+    let categories = xlsx::sheet_order(&project);
+    let mut zip = ZipWriter::new(Cursor::new(vec![]));
+
+    // The mimetype must come first, uncompressed
+    zip.start_file(
+        "mimetype",
+        FileOptions::default().compression_method(CompressionMethod::Stored),
+    )?;
+    zip.write_all(MIMETYPE.as_bytes())?;
+
+    for (path, data) in [
+        ("META-INF/manifest.xml", MANIFEST.to_string()),
+        ("content.xml", content(&categories)),
+        ("settings.xml", settings(&categories)),
+    ] {
+        zip.start_file(path, FileOptions::default())?;
+        zip.write_all(data.as_bytes())?;
+    }
+
+    Ok(PathNode::File(zip.finish()?.into_inner()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_spaces() {
+        let mut out = String::new();
+        write_text(&mut out, "  Two  spaces\tand <tags> \nnext");
+        assert_eq!(
+            out,
+            concat!(
+                "<text:p><text:s/><text:s/>Two <text:s/>spaces<text:tab/>and &lt;tags&gt;<text:s/></text:p>",
+                "<text:p>next</text:p>",
+            )
+        );
+    }
+}
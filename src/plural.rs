@@ -0,0 +1,384 @@
+//! CLDR plural categories and helpers for recognising plural selects in Fluent
+//! patterns, for targets that model plurals as a fixed set of quantities.
+//!
+//! Every locale's categories come from the CLDR plural rules compiled into
+//! `icu`, so targets agree with each other on which forms a locale has.
+
+use icu::{
+    locid::LanguageIdentifier,
+    plurals::{
+        provider::{Baked, CardinalV1Marker},
+        rules::reference::ast,
+        PluralCategory, PluralRules,
+    },
+};
+use icu_provider::{DataProvider, DataRequest};
+
+use crate::ir::{Element, Expression, Pattern, Select, Variant, VariantKey};
+
+/// Every CLDR plural category, in canonical order.
+pub const CATEGORIES: [&str; 6] = ["zero", "one", "two", "few", "many", "other"];
+
+/// The CLDR name of a plural category.
+pub fn category_name(category: PluralCategory) -> &'static str {
+    match category {
+        PluralCategory::Zero => "zero",
+        PluralCategory::One => "one",
+        PluralCategory::Two => "two",
+        PluralCategory::Few => "few",
+        PluralCategory::Many => "many",
+        PluralCategory::Other => "other",
+    }
+}
+
+/// The cardinal plural rules of the given locale, or of the closest locale
+/// CLDR has rules for.
+pub fn cardinal_rules(locale: &LanguageIdentifier) -> PluralRules {
+    PluralRules::try_new_cardinal(&locale.into())
+        .expect("plural rules fall back to the root locale")
+}
+
+/// The cardinal plural categories used by the given locale, in canonical
+/// order.
+pub fn cardinal_categories(locale: &LanguageIdentifier) -> Vec<&'static str> {
+    cardinal_rules(locale)
+        .categories()
+        .map(category_name)
+        .collect()
+}
+
+/// Whole numbers that between them reach every category a rule gives whole
+/// numbers: every remainder of 100 and 1000, and the powers of ten that rules
+/// single out for large numbers, such as French `many`.
+fn integer_samples() -> impl Iterator<Item = u32> {
+    (0..=1000).chain([10_000, 100_000, 1_000_000])
+}
+
+/// The gettext `Plural-Forms` header for a locale, along with the CLDR
+/// category each `msgstr[n]` index stands for.
+#[derive(Debug, Clone)]
+pub struct GettextPlurals {
+    pub forms: String,
+    pub categories: Vec<&'static str>,
+}
+
+/// The gettext plural forms for the given locale: the categories of
+/// [`cardinal_categories`] that whole numbers can have, chosen by the same
+/// CLDR rules written as a C expression.
+pub fn gettext_plurals(locale: &LanguageIdentifier) -> GettextPlurals {
+    let rules = cardinal_rules(locale);
+    let used = integer_samples()
+        .map(|n| rules.category_for(n))
+        .collect::<Vec<_>>();
+    let categories = rules
+        .categories()
+        .filter(|x| used.contains(x))
+        .collect::<Vec<_>>();
+
+    let data = DataProvider::<CardinalV1Marker>::load(
+        &Baked,
+        DataRequest {
+            locale: &locale.into(),
+            metadata: Default::default(),
+        },
+    )
+    .and_then(|x| x.take_payload())
+    .expect("plural rules fall back to the root locale");
+    let data = data.get();
+
+    // Each form but the last is chosen by its rule, in the order CLDR tests
+    // them; the last form takes every other number.
+    let mut plural = String::new();
+    for (i, category) in categories.iter().enumerate().take(categories.len() - 1) {
+        let rule = match category {
+            PluralCategory::Zero => &data.zero,
+            PluralCategory::One => &data.one,
+            PluralCategory::Two => &data.two,
+            PluralCategory::Few => &data.few,
+            PluralCategory::Many => &data.many,
+            PluralCategory::Other => unreachable!("other is always the last category"),
+        };
+        let rule = ast::Rule::from(rule.as_ref().expect("categories have a rule"));
+        plural.push_str(&format!("{} ? {} : ", c_condition(&rule.condition), i));
+    }
+    plural.push_str(&(categories.len() - 1).to_string());
+
+    GettextPlurals {
+        forms: format!("nplurals={}; plural=({});", categories.len(), plural),
+        categories: categories.into_iter().map(category_name).collect(),
+    }
+}
+
+/// A CLDR rule condition as a gettext C expression on the whole number `n`,
+/// for which the fraction and exponent operands are all 0.
+fn c_condition(condition: &ast::Condition) -> String {
+    let mut any = vec![];
+    for and in condition.0.iter() {
+        let mut all = vec![];
+        let mut matches = true;
+        for relation in and.0.iter() {
+            let variable = match relation.expression.operand {
+                ast::Operand::N | ast::Operand::I => match &relation.expression.modulus {
+                    Some(modulus) => format!("n%{}", modulus.0),
+                    None => "n".to_string(),
+                },
+                _ => {
+                    // The operand is 0, so the relation is either always or
+                    // never true
+                    let contains = relation.range_list.0.iter().any(|x| match x {
+                        ast::RangeListItem::Range(range) => range.start().0 == 0,
+                        ast::RangeListItem::Value(value) => value.0 == 0,
+                    });
+                    matches &= contains == (relation.operator == ast::Operator::Eq);
+                    continue;
+                }
+            };
+
+            let negated = relation.operator == ast::Operator::NotEq;
+            let items = relation
+                .range_list
+                .0
+                .iter()
+                .map(|x| match (x, negated) {
+                    (ast::RangeListItem::Value(value), false) => {
+                        format!("{variable}=={}", value.0)
+                    }
+                    (ast::RangeListItem::Value(value), true) => {
+                        format!("{variable}!={}", value.0)
+                    }
+                    (ast::RangeListItem::Range(range), false) => format!(
+                        "{variable}>={} && {variable}<={}",
+                        range.start().0,
+                        range.end().0
+                    ),
+                    (ast::RangeListItem::Range(range), true) => format!(
+                        "({variable}<{} || {variable}>{})",
+                        range.start().0,
+                        range.end().0
+                    ),
+                })
+                .collect::<Vec<_>>();
+
+            match (items.len(), negated) {
+                (1, _) | (_, true) => all.extend(items),
+                _ => all.push(format!("({})", items.join(" || "))),
+            }
+        }
+
+        if !matches {
+            continue;
+        }
+        if all.is_empty() {
+            return "1".to_string();
+        }
+        any.push(all.join(" && "));
+    }
+
+    match any.len() {
+        0 => "0".to_string(),
+        1 => any.remove(0),
+        _ => any
+            .iter()
+            .map(|x| format!("({x})"))
+            .collect::<Vec<_>>()
+            .join(" || "),
+    }
+}
+
+/// Whether every variant of a select is keyed by a plural category or an
+/// exact number.
+pub fn is_plural_select(select: &Select) -> bool {
+    select.variants.iter().all(|x| match &x.key {
+        VariantKey::Identifier(name) => CATEGORIES.contains(&name.as_str()),
+        VariantKey::Number(_) => true,
+    })
+}
+
+/// A pattern whose only top-level select expression selects on plural
+/// categories, such as `{ $count -> [one] ... *[other] ... }`.
+#[derive(Debug, Clone, Copy)]
+pub struct PluralSelect<'a> {
+    pub variable: &'a str,
+    pattern: &'a Pattern,
+    index: usize,
+}
+
+impl<'a> PluralSelect<'a> {
+    pub fn from_pattern(pattern: &'a Pattern) -> Option<Self> {
+        let mut selects = pattern
+            .elements
+            .iter()
+            .enumerate()
+            .filter_map(|(i, x)| match x {
+                Element::Select(select) => Some((i, select)),
+                _ => None,
+            });
+
+        let (index, select) = selects.next()?;
+        if selects.next().is_some() {
+            return None;
+        }
+
+        let variable = selector_variable(&select.selector)?;
+        is_plural_select(select).then_some(Self {
+            variable,
+            pattern,
+            index,
+        })
+    }
+
+    /// The plural categories explicitly handled by the select's variants.
+    pub fn categories(&self) -> impl Iterator<Item = &'a str> {
+        self.variants().iter().filter_map(|x| match &x.key {
+            VariantKey::Identifier(name) => Some(name.as_str()),
+            VariantKey::Number(_) => None,
+        })
+    }
+
+    /// The exact numbers handled by the select's variants, such as `[0]`.
+    pub fn numbers(&self) -> impl Iterator<Item = &'a str> {
+        self.variants().iter().filter_map(|x| match &x.key {
+            VariantKey::Number(value) => Some(value.as_str()),
+            VariantKey::Identifier(_) => None,
+        })
+    }
+
+    /// The pattern with the select expression replaced by the variant for the
+    /// given category, or the default variant if there is no such variant.
+    pub fn expand(&self, category: &str) -> Pattern {
+        let variants = self.variants();
+        let variant = variants
+            .iter()
+            .find(|x| matches!(&x.key, VariantKey::Identifier(name) if name == category))
+            .or_else(|| variants.iter().find(|x| x.default))
+            .expect("select expression has no default variant");
+
+        self.substitute(variant)
+    }
+
+    /// Like [`expand`](Self::expand), but preferring a variant for exactly
+    /// `number` if there is one.
+    pub fn expand_number(&self, number: u32, category: &str) -> Pattern {
+        let variant = self
+            .variants()
+            .iter()
+            .find(|x| matches!(&x.key, VariantKey::Number(value) if value.parse() == Ok(number)));
+
+        match variant {
+            Some(variant) => self.substitute(variant),
+            None => self.expand(category),
+        }
+    }
+
+    fn substitute(&self, variant: &Variant) -> Pattern {
+        let elements = &self.pattern.elements;
+        let mut out = Pattern::default();
+        for element in elements[..self.index]
+            .iter()
+            .chain(variant.value.elements.iter())
+            .chain(elements[self.index + 1..].iter())
+        {
+            out.push(element.clone());
+        }
+        out
+    }
+
+    fn variants(&self) -> &'a [Variant] {
+        match &self.pattern.elements[self.index] {
+            Element::Select(select) => &select.variants,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Builds a plural select on `variable` from patterns keyed by plural
+/// category or exact number. `other`, or failing that the last variant, is
+/// the default.
+pub fn select(variable: &str, variants: Vec<(String, Pattern)>) -> Pattern {
+    Pattern::select(Expression::Variable(variable.to_string()), variants)
+}
+
+/// The variable a select expression selects on, either directly or through
+/// `NUMBER()`.
+fn selector_variable(selector: &Expression) -> Option<&str> {
+    match selector {
+        Expression::Variable(name) => Some(name),
+        Expression::Function { name, arguments } if name == "NUMBER" => {
+            match arguments.positional.first() {
+                Some(Expression::Variable(name)) => Some(name),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flt::parse_pattern;
+
+    #[test]
+    fn expand_plural_select() {
+        let pattern = parse_pattern(
+            "Found { $count ->\n    [one] one file\n   *[other] { $count } files\n} here",
+        )
+        .unwrap();
+        let select = PluralSelect::from_pattern(&pattern).unwrap();
+        assert_eq!(select.variable, "count");
+        assert_eq!(
+            select.categories().collect::<Vec<_>>(),
+            vec!["one", "other"]
+        );
+
+        let few = select.expand("few");
+        assert_eq!(
+            few.elements,
+            vec![
+                Element::Text("Found ".into()),
+                Element::Placeable(Expression::Variable("count".into())),
+                Element::Text(" files here".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn non_plural_select() {
+        let pattern = parse_pattern("{ $gender ->\n    [male] his\n   *[other] their\n}").unwrap();
+        assert!(PluralSelect::from_pattern(&pattern).is_none());
+    }
+
+    #[test]
+    fn categories_from_cldr() {
+        let categories = |x: &str| cardinal_categories(&x.parse().unwrap());
+        assert_eq!(categories("ja"), vec!["other"]);
+        assert_eq!(categories("de-AT"), vec!["one", "other"]);
+        assert_eq!(categories("ru"), vec!["one", "few", "many", "other"]);
+        assert_eq!(
+            categories("ar"),
+            vec!["zero", "one", "two", "few", "many", "other"]
+        );
+    }
+
+    #[test]
+    fn gettext_plurals_from_cldr() {
+        let plurals = |x: &str| gettext_plurals(&x.parse().unwrap());
+
+        let en = plurals("en");
+        assert_eq!(en.forms, "nplurals=2; plural=(n==1 ? 0 : 1);");
+        assert_eq!(en.categories, vec!["one", "other"]);
+
+        assert_eq!(plurals("ja").forms, "nplurals=1; plural=(0);");
+
+        // Russian `other` is only for fractions
+        let ru = plurals("ru");
+        assert_eq!(
+            ru.forms,
+            concat!(
+                "nplurals=3; plural=(n%10==1 && n%100!=11 ? 0 : ",
+                "n%10>=2 && n%10<=4 && (n%100<12 || n%100>14) ? 1 : 2);"
+            )
+        );
+        assert_eq!(ru.categories, vec!["one", "few", "many"]);
+    }
+}
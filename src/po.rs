@@ -0,0 +1,626 @@
+//! gettext PO files for Weblate/Pootle-style translation workflows.
+//!
+//! Each category becomes a directory with a `.pot` template built from its
+//! base strings and a `.po` file per other locale. Entries are identified by
+//! `msgctxt`, which carries the category key along with the message key (and
+//! attribute, as `<message>__<attribute>` like [`crate::xlsx`]), so that the
+//! same source text can be translated differently in different places.
+//!
+//! Strings are kept as Fluent pattern source. Plural selects are the one
+//! exception: they become `msgid_plural`/`msgstr[n]` entries using the
+//! gettext plural forms of each locale.
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use fluent_syntax::parser::ParserError;
+use heck::ToTitleCase;
+use icu::locid::LanguageIdentifier;
+
+use crate::{
+    flt::{parse_pattern_lossy, pattern_source},
+    ir::{
+        CIdentifier, Category, Keys, Pattern, Project, TUIdentifier, TranslationUnit,
+        TranslationUnitMap,
+    },
+    plural::{self, PluralSelect},
+    PathNode,
+};
+
+/// Flag marking the variable a plural entry selects on, which is otherwise
+/// lost when the select is split into `msgstr[n]` strings.
+const PLURAL_VARIABLE_FLAG: &str = "plural-variable:";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Translation {
+    Single(String),
+    Plural(Vec<String>),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PoEntry {
+    pub extracted_comments: Vec<String>,
+    pub flags: Vec<String>,
+    pub msgctxt: Option<String>,
+    pub msgid: String,
+    pub msgid_plural: Option<String>,
+    pub msgstr: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PoFile {
+    pub headers: Vec<(String, String)>,
+    pub entries: Vec<PoEntry>,
+}
+
+impl PoFile {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some(c) => out.push(c),
+            None => {}
+        }
+    }
+    out
+}
+
+/// Writes a keyword and its string, splitting multiline strings after each
+/// newline the way `msgmerge` does.
+fn write_string(out: &mut String, keyword: &str, value: &str) {
+    if !value.contains('\n') || value.trim_end_matches('\n').is_empty() {
+        out.push_str(&format!("{keyword} \"{}\"\n", escape(value)));
+        return;
+    }
+
+    out.push_str(&format!("{keyword} \"\"\n"));
+    for line in value.split_inclusive('\n') {
+        out.push_str(&format!("\"{}\"\n", escape(line)));
+    }
+}
+
+fn write_entry(out: &mut String, entry: &PoEntry) {
+    for comment in entry.extracted_comments.iter() {
+        out.push_str(&format!("#. {comment}\n"));
+    }
+    if !entry.flags.is_empty() {
+        out.push_str(&format!("#, {}\n", entry.flags.join(", ")));
+    }
+    if let Some(msgctxt) = entry.msgctxt.as_ref() {
+        write_string(out, "msgctxt", msgctxt);
+    }
+    write_string(out, "msgid", &entry.msgid);
+    match entry.msgid_plural.as_ref() {
+        Some(msgid_plural) => {
+            write_string(out, "msgid_plural", msgid_plural);
+            for (i, msgstr) in entry.msgstr.iter().enumerate() {
+                write_string(out, &format!("msgstr[{i}]"), msgstr);
+            }
+        }
+        None => write_string(
+            out,
+            "msgstr",
+            entry.msgstr.first().map(|x| x.as_str()).unwrap_or_default(),
+        ),
+    }
+    out.push('\n');
+}
+
+impl PoFile {
+    pub fn write(&self) -> String {
+        let mut out = String::new();
+        let header = self
+            .headers
+            .iter()
+            .map(|(k, v)| format!("{k}: {v}\n"))
+            .collect::<String>();
+        write_entry(
+            &mut out,
+            &PoEntry {
+                msgstr: vec![header],
+                ..Default::default()
+            },
+        );
+        for entry in self.entries.iter() {
+            write_entry(&mut out, entry);
+        }
+        out
+    }
+}
+
+fn msgctxt(category: &CIdentifier, key: &TUIdentifier, attr: Option<&TUIdentifier>) -> String {
+    match attr {
+        Some(attr) => format!("{}.{}__{}", category, key, attr),
+        None => format!("{}.{}", category, key),
+    }
+}
+
+/// Splits a value into plural forms if it is a plural select, keyed by CLDR
+/// category.
+fn translation(pattern: &Pattern, categories: &[&str]) -> (Translation, Option<String>) {
+    match PluralSelect::from_pattern(pattern) {
+        Some(select) => (
+            Translation::Plural(
+                categories
+                    .iter()
+                    .map(|category| pattern_source(&select.expand(category)))
+                    .collect(),
+            ),
+            Some(select.variable.to_string()),
+        ),
+        None => (Translation::Single(pattern_source(pattern)), None),
+    }
+}
+
+fn headers(
+    project_name: &str,
+    category: &Category,
+    locale: Option<&LanguageIdentifier>,
+) -> Vec<(String, String)> {
+    let plural_forms = match locale {
+        Some(locale) => plural::gettext_plurals(locale).forms,
+        None => "nplurals=INTEGER; plural=EXPRESSION;".to_string(),
+    };
+
+    vec![
+        ("Project-Id-Version".into(), project_name.to_string()),
+        (
+            "Language".into(),
+            locale.map(|x| x.to_string()).unwrap_or_default(),
+        ),
+        ("MIME-Version".into(), "1.0".into()),
+        ("Content-Type".into(), "text/plain; charset=UTF-8".into()),
+        ("Content-Transfer-Encoding".into(), "8bit".into()),
+        ("Plural-Forms".into(), plural_forms),
+        (
+            "X-Source-Language".into(),
+            category.default_locale.to_string(),
+        ),
+        ("X-Category-Name".into(), category.name.clone()),
+    ]
+}
+
+fn generate_file(
+    project_name: &str,
+    category: &Category,
+    target: Option<&TranslationUnitMap>,
+) -> PoFile {
+    let base = category.base_strings();
+    let target_categories = match target {
+        Some(target) => plural::gettext_plurals(&target.locale).categories,
+        None => vec!["one", "other"],
+    };
+
+    let mut entries = vec![];
+    for (key, attr) in category.ordered_tu_identity_keys() {
+        let Some(source) = base.get(key).and_then(|x| x.value(attr)) else {
+            continue;
+        };
+
+        let target = target.and_then(|x| x.get(key)).and_then(|x| x.value(attr));
+
+        let mut entry = PoEntry {
+            extracted_comments: category
+                .descriptions
+                .get(key)
+                .map(|x| x.lines().map(str::to_string).collect())
+                .unwrap_or_default(),
+            msgctxt: Some(msgctxt(&category.key, key, attr)),
+            ..Default::default()
+        };
+
+        match translation(source, &["one", "other"]) {
+            // Selects in translations of non-plural strings are kept as they are
+            (Translation::Single(source), _) => {
+                entry.msgid = source;
+                entry.msgstr = vec![match target {
+                    Some(value) => pattern_source(value),
+                    None => String::new(),
+                }];
+            }
+            (Translation::Plural(mut forms), variable) => {
+                if let Some(variable) = variable {
+                    entry
+                        .flags
+                        .push(format!("{PLURAL_VARIABLE_FLAG}{variable}"));
+                }
+                entry.msgid_plural = forms.pop();
+                entry.msgid = forms.pop().unwrap_or_default();
+                entry.msgstr = match target {
+                    Some(value) => match translation(value, &target_categories).0 {
+                        Translation::Plural(forms) => forms,
+                        Translation::Single(x) => vec![x; target_categories.len()],
+                    },
+                    None => vec![String::new(); target_categories.len()],
+                };
+            }
+        }
+
+        entries.push(entry);
+    }
+
+    PoFile {
+        headers: headers(project_name, category, target.map(|x| &x.locale)),
+        entries,
+    }
+}
+
+pub fn generate(input: Project) -> Result<PathNode, ParserError> {
+    let mut files = BTreeMap::new();
+
+    for (category_id, category) in input.categories.iter() {
+        let mut subfiles = BTreeMap::new();
+
+        subfiles.insert(
+            format!("{category_id}.pot"),
+            PathNode::File(
+                generate_file(&input.name, category, None)
+                    .write()
+                    .into_bytes(),
+            ),
+        );
+
+        for (locale, target) in category.translation_units.iter() {
+            if *locale == category.default_locale {
+                continue;
+            }
+
+            subfiles.insert(
+                format!("{locale}.po"),
+                PathNode::File(
+                    generate_file(&input.name, category, Some(target))
+                        .write()
+                        .into_bytes(),
+                ),
+            );
+        }
+
+        files.insert(category_id.to_string(), PathNode::Directory(subfiles));
+    }
+
+    Ok(PathNode::Directory(files))
+}
+
+/// Parses the quoted string at the start of `value`, returning its unescaped
+/// contents.
+fn parse_quoted(value: &str) -> anyhow::Result<String> {
+    let value = value.trim();
+    let Some(inner) = value.strip_prefix('"').and_then(|x| x.strip_suffix('"')) else {
+        anyhow::bail!("Expected a quoted string, found: {value}");
+    };
+    Ok(unescape(inner))
+}
+
+fn finish_entry(file: &mut PoFile, entry: &mut PoEntry, has_msgid: &mut bool) {
+    let entry = std::mem::take(entry);
+    if !std::mem::take(has_msgid) {
+        return;
+    }
+
+    // The entry with an empty msgid holds the headers
+    if entry.msgid.is_empty() && entry.msgctxt.is_none() {
+        file.headers = entry
+            .msgstr
+            .first()
+            .map(|x| x.as_str())
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|x| x.split_once(':'))
+            .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+            .collect();
+    } else {
+        file.entries.push(entry);
+    }
+}
+
+pub fn parse_po(input: &str) -> anyhow::Result<PoFile> {
+    let mut file = PoFile::default();
+    let mut entry = PoEntry::default();
+    let mut has_msgid = false;
+    // The string that continuation lines are appended to
+    let mut current: Option<(&str, usize)> = None;
+
+    for (n, line) in input.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() {
+            finish_entry(&mut file, &mut entry, &mut has_msgid);
+            current = None;
+            continue;
+        }
+
+        // Entries are usually separated by blank lines, but don't have to be
+        let starts_entry =
+            line.starts_with('#') || line.starts_with("msgctxt ") || line.starts_with("msgid ");
+        if starts_entry && matches!(current, Some(("msgstr", _))) {
+            finish_entry(&mut file, &mut entry, &mut has_msgid);
+            current = None;
+        }
+
+        if let Some(comment) = line.strip_prefix("#.") {
+            entry.extracted_comments.push(comment.trim().to_string());
+        } else if let Some(flags) = line.strip_prefix("#,") {
+            entry
+                .flags
+                .extend(flags.split(',').map(|x| x.trim().to_string()));
+        } else if line.starts_with('#') {
+            // Translator comments, references and obsolete entries
+            continue;
+        } else if line.starts_with('"') {
+            let value = parse_quoted(line)?;
+            match current {
+                Some(("msgctxt", _)) => entry
+                    .msgctxt
+                    .get_or_insert_with(String::new)
+                    .push_str(&value),
+                Some(("msgid", _)) => entry.msgid.push_str(&value),
+                Some(("msgid_plural", _)) => entry
+                    .msgid_plural
+                    .get_or_insert_with(String::new)
+                    .push_str(&value),
+                Some((_, i)) => entry.msgstr[i].push_str(&value),
+                None => anyhow::bail!("Unexpected string on line {}", n + 1),
+            }
+        } else {
+            let (keyword, value) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| anyhow::anyhow!("Invalid line {}: {line}", n + 1))?;
+            let value = parse_quoted(value)?;
+
+            match keyword {
+                "msgctxt" => {
+                    entry.msgctxt = Some(value);
+                    current = Some(("msgctxt", 0));
+                }
+                "msgid" => {
+                    entry.msgid = value;
+                    has_msgid = true;
+                    current = Some(("msgid", 0));
+                }
+                "msgid_plural" => {
+                    entry.msgid_plural = Some(value);
+                    current = Some(("msgid_plural", 0));
+                }
+                "msgstr" => {
+                    entry.msgstr = vec![value];
+                    current = Some(("msgstr", 0));
+                }
+                _ => {
+                    let Some(index) = keyword
+                        .strip_prefix("msgstr[")
+                        .and_then(|x| x.strip_suffix(']'))
+                        .and_then(|x| x.parse::<usize>().ok())
+                    else {
+                        anyhow::bail!("Unknown keyword on line {}: {keyword}", n + 1);
+                    };
+                    if entry.msgstr.len() <= index {
+                        entry.msgstr.resize(index + 1, String::new());
+                    }
+                    entry.msgstr[index] = value;
+                    current = Some(("msgstr", index));
+                }
+            }
+        }
+    }
+
+    finish_entry(&mut file, &mut entry, &mut has_msgid);
+    Ok(file)
+}
+
+fn insert_value(
+    category: &mut Category,
+    locale: &LanguageIdentifier,
+    key: &TUIdentifier,
+    attr: Option<&TUIdentifier>,
+    value: Pattern,
+) {
+    let unit = category
+        .entry(locale.clone())
+        .or_insert_with(|| TranslationUnitMap::new(locale.clone()))
+        .entry(key.clone())
+        .or_insert_with(|| TranslationUnit::new(key.clone()));
+
+    match attr {
+        Some(attr) => {
+            unit.attributes.insert(attr.clone(), value);
+        }
+        None => unit.main = value,
+    }
+}
+
+/// Rebuilds the plural select of an entry from strings in the order of the
+/// given plural categories.
+fn plural_pattern(entry: &PoEntry, forms: &[String], categories: &[&str]) -> Pattern {
+    let variable = entry
+        .flags
+        .iter()
+        .find_map(|x| x.strip_prefix(PLURAL_VARIABLE_FLAG))
+        .unwrap_or("count");
+
+    let variants = categories
+        .iter()
+        .zip(forms.iter())
+        .map(|(category, value)| (category.to_string(), parse_pattern_lossy(value)))
+        .collect::<Vec<_>>();
+
+    plural::select(variable, variants)
+}
+
+fn load_file(project: &mut Project, keys: &mut Keys, path: &Path) -> anyhow::Result<()> {
+    let file = parse_po(&std::fs::read_to_string(path)?)?;
+    let is_template = path.extension().and_then(|x| x.to_str()) == Some("pot");
+
+    let source_locale =
+        LanguageIdentifier::from_str(file.header("X-Source-Language").unwrap_or("en"))?;
+    let locale = match file.header("Language").filter(|x| !x.is_empty()) {
+        Some(x) if !is_template => Some(LanguageIdentifier::from_str(&x.replace('_', "-"))?),
+        _ => None,
+    };
+
+    for entry in file.entries.iter() {
+        if entry.flags.iter().any(|x| x == "fuzzy") && !is_template {
+            continue;
+        }
+
+        let Some((category_id, unit_id)) = entry.msgctxt.as_ref().and_then(|x| x.split_once('.'))
+        else {
+            eprintln!(
+                "[{}] Entry without a stringly msgctxt: {:?}; skipping",
+                path.display(),
+                entry.msgid
+            );
+            continue;
+        };
+
+        let category_id = keys.category(category_id)?;
+        let mut chunks = unit_id.splitn(2, "__");
+        let key = keys.unit(&category_id, chunks.next().unwrap())?;
+        let attr = chunks
+            .next()
+            .map(|x| keys.attribute(&category_id, &key, x))
+            .transpose()?;
+        let category = project
+            .entry(category_id.clone())
+            .or_insert_with(|| Category {
+                key: category_id.clone(),
+                name: file
+                    .header("X-Category-Name")
+                    .map(str::to_string)
+                    .unwrap_or_else(|| category_id.to_title_case()),
+                default_locale: source_locale.clone(),
+                descriptions: Default::default(),
+                notes: None,
+                sections: Default::default(),
+                translation_units: Default::default(),
+            });
+
+        if attr.is_none() && !entry.extracted_comments.is_empty() {
+            category
+                .descriptions
+                .insert(key.clone(), entry.extracted_comments.join("\n"));
+        }
+
+        let source = match entry.msgid_plural.as_ref() {
+            Some(msgid_plural) => plural_pattern(
+                entry,
+                &[entry.msgid.clone(), msgid_plural.clone()],
+                &["one", "other"],
+            ),
+            None => parse_pattern_lossy(&entry.msgid),
+        };
+        insert_value(category, &source_locale, &key, attr.as_ref(), source);
+
+        let Some(locale) = locale.as_ref() else {
+            continue;
+        };
+        if entry.msgstr.iter().all(|x| x.is_empty()) {
+            continue;
+        }
+
+        let value = match entry.msgid_plural.as_ref() {
+            Some(_) => plural_pattern(
+                entry,
+                &entry.msgstr,
+                &plural::gettext_plurals(locale).categories,
+            ),
+            None => parse_pattern_lossy(&entry.msgstr[0]),
+        };
+        insert_value(category, locale, &key, attr.as_ref(), value);
+    }
+
+    Ok(())
+}
+
+/// Loads every `.pot` and `.po` file below the given directory.
+pub fn load_project_from_path(path: &Path, keys: &mut Keys) -> anyhow::Result<Project> {
+    let mut project = Project::default();
+
+    let mut files = walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .map(|x| x.into_path())
+        .filter(|x| matches!(x.extension().and_then(|x| x.to_str()), Some("po" | "pot")))
+        .collect::<Vec<PathBuf>>();
+    // Templates first, so translations can override nothing but their own
+    // locale.
+    files.sort_by_key(|x| x.extension().and_then(|x| x.to_str()) != Some("pot"));
+
+    for path in files {
+        load_file(&mut project, keys, &path)?;
+    }
+
+    Ok(project)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_entries() {
+        let file = parse_po(concat!(
+            "msgid \"\"\n",
+            "msgstr \"\"\n",
+            "\"Language: de\\n\"\n",
+            "\n",
+            "#. A description\n",
+            "#, fuzzy, plural-variable:count\n",
+            "msgctxt \"core.files\"\n",
+            "msgid \"One \\\"file\\\"\"\n",
+            "msgid_plural \"\"\n",
+            "\"{ $count } \"\n",
+            "\"files\"\n",
+            "msgstr[0] \"Eine Datei\"\n",
+            "msgstr[1] \"{ $count } Dateien\"\n",
+            "msgctxt \"core.plain\"\n",
+            "msgid \"Text\"\n",
+            "msgstr \"\"\n",
+        ))
+        .unwrap();
+
+        assert_eq!(file.header("language"), Some("de"));
+        assert_eq!(file.entries.len(), 2);
+
+        let entry = &file.entries[0];
+        assert_eq!(entry.extracted_comments, vec!["A description"]);
+        assert_eq!(entry.flags, vec!["fuzzy", "plural-variable:count"]);
+        assert_eq!(entry.msgid, "One \"file\"");
+        assert_eq!(entry.msgid_plural.as_deref(), Some("{ $count } files"));
+        assert_eq!(entry.msgstr, vec!["Eine Datei", "{ $count } Dateien"]);
+        assert_eq!(file.entries[1].msgctxt.as_deref(), Some("core.plain"));
+    }
+}
@@ -0,0 +1,241 @@
+//! printf-style format strings, as used by Android and Apple platforms.
+//!
+//! Arguments are always written positionally (`%1$s`) since translations are
+//! free to reorder them. Variables imported from a position (`$arg2`) keep
+//! it, and named Fluent variables take the remaining positions by their order
+//! of appearance in the base string.
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use crate::{
+    flt::render::{self, Segment, VariableKind},
+    ir::{Arguments, Element, Expression, Pattern, TUIdentifier, TranslationUnitMap},
+};
+
+/// The conversions a platform uses for string and integer arguments.
+#[derive(Debug, Clone, Copy)]
+pub struct Conversions {
+    pub string: &'static str,
+    pub number: &'static str,
+}
+
+pub const ANDROID: Conversions = Conversions {
+    string: "s",
+    number: "d",
+};
+
+pub const APPLE: Conversions = Conversions {
+    string: "@",
+    number: "lld",
+};
+
+static FORMAT_REGEX: OnceLock<Regex> = OnceLock::new();
+
+fn format_regex() -> &'static Regex {
+    FORMAT_REGEX.get_or_init(|| {
+        Regex::new(r"%(?:(\d+)\$)?[-#+0,(]*\d*(?:\.\d+)?(?:hh|h|ll|l|q|z|t|j)?([@%aAbBcCdeEfFgGhHinosSuxX])")
+            .unwrap()
+    })
+}
+
+/// The position a variable was imported from, e.g. 2 for `$arg2`.
+pub fn position(name: &str) -> Option<usize> {
+    name.strip_prefix("arg")?.parse().ok().filter(|x| *x > 0)
+}
+
+/// The argument order of a message or attribute. This is fixed by the base
+/// strings so that every locale takes the same arguments. Variables imported
+/// from a position keep it, so `%2$s %1$d` isn't renumbered on export; the
+/// rest fill the remaining positions in order of appearance.
+pub fn arguments(
+    base: &TranslationUnitMap,
+    key: &TUIdentifier,
+    attr: Option<&TUIdentifier>,
+) -> Vec<String> {
+    let Some(value) = base.get(key).and_then(|x| x.value(attr)) else {
+        return vec![];
+    };
+    let names = render::variables(value, base)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>();
+
+    let mut args: Vec<Option<String>> = vec![];
+    for name in &names {
+        if let Some(index) = position(name) {
+            if args.len() < index {
+                args.resize(index, None);
+            }
+            args[index - 1] = Some(name.clone());
+        }
+    }
+
+    let mut rest = names.into_iter().filter(|x| position(x).is_none());
+    for arg in args.iter_mut().filter(|x| x.is_none()) {
+        *arg = rest.next();
+    }
+
+    // Positions nothing is left to fill are taken by placeholders no
+    // pattern refers to, so the ones after them stay where they are.
+    args.into_iter()
+        .enumerate()
+        .map(|(i, x)| x.unwrap_or_else(|| format!("arg{}", i + 1)))
+        .chain(rest)
+        .collect()
+}
+
+/// Renders segments as a format string. `args` holds the argument order and
+/// grows as new variables are found; variables in `numbers` are always
+/// formatted as integers. Text is passed through `escape` and, if the string
+/// takes any arguments, has its `%` signs doubled.
+pub fn format(
+    segments: &[Segment],
+    args: &mut Vec<String>,
+    numbers: &[&str],
+    conversions: Conversions,
+    escape: impl Fn(&str) -> String,
+) -> String {
+    let formatted =
+        !numbers.is_empty() || segments.iter().any(|x| matches!(x, Segment::Variable(..)));
+
+    segments
+        .iter()
+        .map(|x| match x {
+            Segment::Text(text) if formatted => escape(text).replace('%', "%%"),
+            Segment::Text(text) => escape(text),
+            Segment::Variable(name, kind) => {
+                let index = render::argument_index(args, name);
+                let conversion =
+                    if *kind == VariableKind::Number || numbers.contains(&name.as_str()) {
+                        conversions.number
+                    } else {
+                        conversions.string
+                    };
+                format!("%{}${}", index + 1, conversion)
+            }
+        })
+        .collect()
+}
+
+/// Whether a format string takes arguments, rather than being shown as is.
+/// [`format`] numbers every argument and doubles `%` signs, so any such
+/// specifier means it does. Unnumbered ones only count if none of them runs
+/// into a word, so `50%off` is text while `%d files` takes an integer.
+pub fn takes_arguments(value: &str) -> bool {
+    let mut found = false;
+
+    for c in format_regex().captures_iter(value) {
+        match &c[2] {
+            "n" => continue,
+            "%" => return true,
+            _ if c.get(1).is_some() => return true,
+            _ => {}
+        }
+
+        let end = c.get(0).unwrap().end();
+        if value[end..].starts_with(|x: char| x.is_alphabetic()) {
+            return false;
+        }
+        found = true;
+    }
+
+    found
+}
+
+/// Converts a format string into a pattern, mapping arguments such as `%1$s`
+/// to `{ $arg1 }` and integer ones such as `%d` to `{ NUMBER($arg1) }`.
+/// Strings that don't [take arguments](takes_arguments) are kept as text.
+pub fn to_pattern(value: &str) -> Pattern {
+    let mut out = Pattern::default();
+    if !takes_arguments(value) {
+        out.push_text(value);
+        return out;
+    }
+
+    let mut last = 0;
+    let mut next_arg = 1;
+
+    for c in format_regex().captures_iter(value) {
+        let m = c.get(0).unwrap();
+        out.push_text(&value[last..m.start()]);
+        last = m.end();
+
+        match &c[2] {
+            "%" => out.push_text("%"),
+            "n" => out.push_text("\n"),
+            _ => {
+                let index = match c.get(1) {
+                    Some(x) => x.as_str().parse().unwrap_or(next_arg),
+                    None => next_arg,
+                };
+                next_arg = index + 1;
+                let variable = format!("arg{index}");
+                match &c[2] {
+                    "d" | "i" | "u" => out.push(Element::Placeable(Expression::Function {
+                        name: "NUMBER".into(),
+                        arguments: Arguments {
+                            positional: vec![Expression::Variable(variable)],
+                            named: vec![],
+                        },
+                    })),
+                    _ => out.push_variable(&variable),
+                }
+            }
+        }
+    }
+
+    out.push_text(&value[last..]);
+    out
+}
+
+/// The position of the first integer argument of a format string, which is
+/// what platforms pick plural variants by.
+pub fn number_argument(value: &str) -> Option<usize> {
+    if !takes_arguments(value) {
+        return None;
+    }
+
+    let mut next_arg = 1;
+
+    for c in format_regex().captures_iter(value) {
+        let index = match c.get(1) {
+            Some(x) => x.as_str().parse().unwrap_or(next_arg),
+            None => next_arg,
+        };
+
+        match &c[2] {
+            "%" | "n" => continue,
+            "d" | "i" | "u" => return Some(index),
+            _ => next_arg = index + 1,
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_signs_in_text() {
+        assert!(!takes_arguments("50%off"));
+        assert_eq!(
+            to_pattern("50%off").elements,
+            vec![Element::Text("50%off".into())]
+        );
+        assert_eq!(number_argument("Save 50%off %d items"), None);
+
+        assert!(takes_arguments("%d files"));
+        assert!(takes_arguments("%1$sfiles"));
+        assert_eq!(
+            to_pattern("50%% off %1$s").elements,
+            vec![
+                Element::Text("50% off ".into()),
+                Element::Placeable(Expression::Variable("arg1".into())),
+            ]
+        );
+    }
+}
@@ -0,0 +1,744 @@
+//! Java `.properties` resource bundles, one `<category>_<locale>.properties`
+//! per category and locale, plus `<category>.properties` holding the default
+//! locale as the bundle's root.
+//!
+//! Values are `java.text.MessageFormat` patterns taking positional arguments
+//! (`{0}`), ordered by the base strings the same way as [`crate::printf`].
+//! As with printf-style strings, quotes and braces are only escaped in values
+//! that take arguments, and only values with a `{0}`-style argument are read
+//! back as `MessageFormat` patterns. Plural selects become `choice` formats
+//! where the locale's rules can be expressed as ranges. Attributes are stored
+//! as `<message>__<attribute>` keys.
+
+use std::{collections::BTreeMap, path::Path, str::FromStr};
+
+use fluent_syntax::parser::ParserError;
+use heck::ToTitleCase;
+use icu::{
+    locid::{locale, LanguageIdentifier},
+    plurals::PluralCategory,
+};
+
+use crate::{
+    flt::render,
+    ir::{
+        Arguments, Category, Element, Expression, Keys, Pattern, Project, TUIdentifier,
+        TranslationUnit, TranslationUnitMap,
+    },
+    plural::{self, PluralSelect},
+    printf, PathNode,
+};
+
+/// An entry of a `.properties` file. Comments directly preceding the entry
+/// become its description.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Property {
+    pub key: String,
+    pub value: String,
+    pub description: Option<String>,
+}
+
+fn java_locale(locale: &LanguageIdentifier) -> String {
+    locale.to_string().replace('-', "_")
+}
+
+fn property_key(key: &TUIdentifier, attr: Option<&TUIdentifier>) -> String {
+    match attr {
+        Some(attr) => format!("{}__{}", key, attr),
+        None => key.to_string(),
+    }
+}
+
+/// Escapes a key or value for a `.properties` file, which is read as
+/// ISO-8859-1 by older JVMs, so anything outside ASCII is written as `\uXXXX`.
+fn escape(value: &str, is_key: bool) -> String {
+    let mut out = String::with_capacity(value.len());
+
+    for (i, c) in value.chars().enumerate() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\x0c' => out.push_str("\\f"),
+            ' ' if is_key || i == 0 => out.push_str("\\ "),
+            '=' | ':' if is_key => {
+                out.push('\\');
+                out.push(c);
+            }
+            '#' | '!' if is_key && i == 0 => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if c.is_ascii() && !c.is_ascii_control() => out.push(c),
+            c => {
+                let mut units = [0u16; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    out.push_str(&format!("\\u{:04X}", unit));
+                }
+            }
+        }
+    }
+
+    out
+}
+
+fn unescape(value: &str) -> anyhow::Result<String> {
+    let mut units = vec![];
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0u16; 2];
+            units.extend_from_slice(c.encode_utf16(&mut buf));
+            continue;
+        }
+
+        let c = match chars.next() {
+            Some('u') => {
+                let hex = chars.by_ref().take(4).collect::<String>();
+                let unit = u16::from_str_radix(&hex, 16)
+                    .map_err(|_| anyhow::anyhow!("Malformed \\u escape: \\u{}", hex))?;
+                units.push(unit);
+                continue;
+            }
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('f') => '\x0c',
+            Some(c) => c,
+            None => break,
+        };
+        let mut buf = [0u16; 2];
+        units.extend_from_slice(c.encode_utf16(&mut buf));
+    }
+
+    // Surrogate pairs are written as two escapes
+    Ok(String::from_utf16_lossy(&units))
+}
+
+/// Joins continued lines (those ending in an odd number of backslashes) and
+/// drops the leading whitespace of each line.
+fn logical_lines(source: &str) -> Vec<String> {
+    let mut out = vec![];
+    let mut current: Option<String> = None;
+
+    for line in source.lines() {
+        let line = line.trim_start_matches([' ', '\t', '\x0c']);
+        let is_comment = current.is_none() && (line.starts_with('#') || line.starts_with('!'));
+        let trailing = line.len() - line.trim_end_matches('\\').len();
+
+        let (text, continues) = if !is_comment && trailing % 2 == 1 {
+            (&line[..line.len() - 1], true)
+        } else {
+            (line, false)
+        };
+
+        let mut joined = current.take().unwrap_or_default();
+        joined.push_str(text);
+        if continues {
+            current = Some(joined);
+        } else {
+            out.push(joined);
+        }
+    }
+
+    out.extend(current);
+    out
+}
+
+/// Parses the entries of a `.properties` file.
+pub fn parse_properties(source: &str) -> anyhow::Result<Vec<Property>> {
+    let mut out = vec![];
+    let mut comments: Vec<String> = vec![];
+
+    for line in logical_lines(source) {
+        if line.is_empty() {
+            comments.clear();
+            continue;
+        }
+
+        if let Some(comment) = line.strip_prefix('#').or_else(|| line.strip_prefix('!')) {
+            comments.push(comment.trim().to_string());
+            continue;
+        }
+
+        let mut key_end = line.len();
+        let mut escaped = false;
+        for (i, c) in line.char_indices() {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if matches!(c, '=' | ':' | ' ' | '\t' | '\x0c') {
+                key_end = i;
+                break;
+            }
+        }
+
+        let rest = line[key_end..].trim_start_matches([' ', '\t', '\x0c']);
+        let rest = rest
+            .strip_prefix(['=', ':'])
+            .unwrap_or(rest)
+            .trim_start_matches([' ', '\t', '\x0c']);
+
+        let description = (!comments.is_empty()).then(|| comments.join("\n"));
+        comments.clear();
+
+        out.push(Property {
+            key: unescape(&line[..key_end])?,
+            value: unescape(rest)?,
+            description,
+        });
+    }
+
+    Ok(out)
+}
+
+/// The plural category of every number up to `max`, if the locale's rules
+/// give every larger number `other`, which is all a `ChoiceFormat` can
+/// express. Numbers are only checked up to 1000: forms kept for exact
+/// millions, such as French `many`, are given the `other` text.
+fn choice_categories(locale: &LanguageIdentifier, max: u32) -> Option<Vec<&'static str>> {
+    let rules = plural::cardinal_rules(locale);
+    (max + 1..=1000)
+        .all(|n| rules.category_for(n) == PluralCategory::Other)
+        .then(|| {
+            (0..=max)
+                .map(|n| plural::category_name(rules.category_for(n)))
+                .collect()
+        })
+}
+
+/// Escapes text for a `MessageFormat` pattern. Text in a choice is unquoted by
+/// `ChoiceFormat` first, and then again by `MessageFormat` if the chosen text
+/// takes arguments.
+fn format_text(text: &str, in_choice: bool, formatted: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\'' if in_choice && formatted => out.push_str("''''"),
+            '\'' => out.push_str("''"),
+            '{' | '}' => out.push_str(&format!("'{c}'")),
+            '|' if in_choice => out.push_str("'|'"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn format_segments(
+    segments: &[render::Segment],
+    args: &mut Vec<String>,
+    in_choice: bool,
+) -> String {
+    let formatted = segments
+        .iter()
+        .any(|x| matches!(x, render::Segment::Variable(..)));
+
+    segments
+        .iter()
+        .map(|x| match x {
+            render::Segment::Text(text) if formatted || in_choice => {
+                format_text(text, in_choice, formatted)
+            }
+            render::Segment::Text(text) => text.clone(),
+            render::Segment::Variable(name, _) => {
+                format!("{{{}}}", render::argument_index(args, name))
+            }
+        })
+        .collect()
+}
+
+/// Writes a pattern as a `MessageFormat` pattern. `args` holds the argument
+/// order and grows as new variables are found.
+fn message_format(
+    key: &str,
+    pattern: &Pattern,
+    units: &TranslationUnitMap,
+    args: &mut Vec<String>,
+) -> String {
+    let Some(select) = PluralSelect::from_pattern(pattern) else {
+        return format_segments(&render::segments(pattern, units), args, false);
+    };
+
+    // Every number up to `max` is given its own choice, merging runs of
+    // identical ones; anything above it is `other`.
+    let max = select
+        .numbers()
+        .filter_map(|x| x.parse::<u32>().ok())
+        .chain([4])
+        .max()
+        .unwrap();
+    let Some(categories) = choice_categories(&units.locale, max) else {
+        eprintln!(
+            "[{}] {} has plural rules a choice format cannot express; using the default variant",
+            units.locale, key
+        );
+        let segments = render::segments(&select.expand("other"), units);
+        return format_segments(&segments, args, false);
+    };
+
+    let index = render::argument_index(args, select.variable);
+    let other = {
+        let segments = render::segments(&select.expand("other"), units);
+        format_segments(&segments, args, true)
+    };
+
+    let mut choices: Vec<(u32, String)> = vec![];
+    for (n, category) in (0..).zip(categories) {
+        let segments = render::segments(&select.expand_number(n, category), units);
+        let text = format_segments(&segments, args, true);
+        if choices.last().map(|(_, x)| x) != Some(&text) {
+            choices.push((n, text));
+        }
+    }
+
+    let mut out = choices
+        .iter()
+        .map(|(n, text)| format!("{n}#{text}"))
+        .collect::<Vec<_>>();
+    if choices.last().map(|(_, x)| x) != Some(&other) {
+        out.push(format!("{max}<{other}"));
+    }
+
+    // The choice is the whole message; any text around the select is part of
+    // each variant already.
+    format!("{{{index},choice,{}}}", out.join("|"))
+}
+
+fn write_properties(entries: &[Property]) -> String {
+    let mut out = String::new();
+
+    for entry in entries {
+        if let Some(description) = entry.description.as_ref() {
+            for line in description.lines() {
+                out.push_str(&format!("# {line}\n"));
+            }
+        }
+        out.push_str(&format!(
+            "{}={}\n",
+            escape(&entry.key, true),
+            escape(&entry.value, false)
+        ));
+    }
+
+    out
+}
+
+pub fn generate(input: Project) -> Result<PathNode, ParserError> {
+    let mut files = BTreeMap::new();
+
+    for (category_id, category) in input.categories.iter() {
+        let base = category.base_strings();
+
+        for locale in category.ordered_locale_keys() {
+            let units = category.get(locale).unwrap();
+            let mut entries = vec![];
+
+            for (key, attr) in category.ordered_tu_identity_keys() {
+                // Terms only exist to be inlined into messages
+                if base[key].is_term() {
+                    continue;
+                }
+
+                let Some(unit) = units.get(key) else {
+                    continue;
+                };
+                let Some(value) = unit.value(attr) else {
+                    continue;
+                };
+                if attr.is_none() && value.is_empty() && !unit.attributes.is_empty() {
+                    continue;
+                }
+
+                let name = property_key(key, attr);
+                let mut args = printf::arguments(base, key, attr);
+                entries.push(Property {
+                    value: message_format(&name, value, units, &mut args),
+                    key: name,
+                    description: category.descriptions.get(key).cloned(),
+                });
+            }
+
+            let source = write_properties(&entries);
+            if *locale == category.default_locale {
+                files.insert(
+                    format!("{category_id}.properties"),
+                    PathNode::File(source.clone().into_bytes()),
+                );
+            }
+            files.insert(
+                format!("{category_id}_{}.properties", java_locale(locale)),
+                PathNode::File(source.into_bytes()),
+            );
+        }
+    }
+
+    Ok(PathNode::Directory(files))
+}
+
+/// Splits a `MessageFormat` pattern at its top-level placeholders, returning
+/// unquoted text and the raw contents of each `{...}`.
+fn split_placeholders(value: &str) -> Vec<Result<String, String>> {
+    let mut out = vec![];
+    let mut text = String::new();
+    let mut chars = value.chars().peekable();
+    let mut quoted = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' if chars.peek() == Some(&'\'') => {
+                chars.next();
+                text.push('\'');
+            }
+            '\'' => quoted = !quoted,
+            '{' if !quoted => {
+                if !text.is_empty() {
+                    out.push(Ok(std::mem::take(&mut text)));
+                }
+                let mut depth = 1;
+                let mut inner_quoted = false;
+                let mut inner = String::new();
+                for c in chars.by_ref() {
+                    match c {
+                        '\'' => inner_quoted = !inner_quoted,
+                        '{' if !inner_quoted => depth += 1,
+                        '}' if !inner_quoted => depth -= 1,
+                        _ => {}
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                    inner.push(c);
+                }
+                out.push(Err(inner));
+            }
+            c => text.push(c),
+        }
+    }
+
+    if !text.is_empty() {
+        out.push(Ok(text));
+    }
+    out
+}
+
+/// Whether a value has a `{0}`-style argument, and so is a `MessageFormat`
+/// pattern rather than plain text.
+fn has_arguments(value: &str) -> bool {
+    split_placeholders(value).iter().any(|x| match x {
+        Err(placeholder) => placeholder
+            .split(',')
+            .next()
+            .is_some_and(|x| x.trim().parse::<usize>().is_ok()),
+        Ok(_) => false,
+    })
+}
+
+/// Converts a `MessageFormat` pattern into a Fluent pattern, naming argument
+/// `{0}` `$arg1` as [`printf::to_pattern`] does. Choice formats become selects
+/// on numeric variants.
+fn to_pattern(value: &str) -> Pattern {
+    // Values without arguments are not run through `MessageFormat`
+    if !has_arguments(value) {
+        return Pattern::text(value);
+    }
+
+    let mut out = Pattern::default();
+    for x in split_placeholders(value) {
+        let placeholder = match x {
+            Ok(text) => {
+                out.push_text(&text);
+                continue;
+            }
+            Err(placeholder) => placeholder,
+        };
+
+        let mut parts = placeholder.splitn(3, ',');
+        let variable = match parts.next().unwrap_or_default().trim().parse::<usize>() {
+            Ok(i) => format!("arg{}", i + 1),
+            Err(_) => {
+                out.push_text(&format!("{{{placeholder}}}"));
+                continue;
+            }
+        };
+
+        match (parts.next().map(str::trim), parts.next()) {
+            (Some("choice"), Some(choices)) => out.extend(choice_pattern(&variable, choices)),
+            (Some("number"), _) => out.push(Element::Placeable(Expression::Function {
+                name: "NUMBER".into(),
+                arguments: Arguments {
+                    positional: vec![Expression::Variable(variable)],
+                    named: vec![],
+                },
+            })),
+            _ => out.push_variable(&variable),
+        }
+    }
+    out
+}
+
+/// Removes `ChoiceFormat` quoting from the text of a choice.
+fn unquote_choice(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' if chars.peek() == Some(&'\'') => {
+                chars.next();
+                out.push('\'');
+            }
+            '\'' => {}
+            c => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Converts the choices of a choice format into a select on `variable`. Each
+/// whole number covered by a choice gets a numeric variant, and the last
+/// choice, which is open-ended, becomes the default.
+fn choice_pattern(variable: &str, choices: &str) -> Pattern {
+    let mut parts = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for c in choices.chars() {
+        match c {
+            '\'' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            '|' if !quoted => parts.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    parts.push(current);
+
+    let choices = parts
+        .iter()
+        .filter_map(|choice| {
+            let split = choice.find(['#', '<', '\u{2264}'])?;
+            let limit = choice[..split].trim().parse::<f64>().ok()?;
+            let start = match &choice[split..] {
+                x if x.starts_with('<') => limit.floor() + 1.0,
+                _ => limit.ceil(),
+            };
+            let text =
+                unquote_choice(&choice[split + choice[split..].chars().next()?.len_utf8()..]);
+            Some((start.max(0.0) as u32, to_pattern(&text)))
+        })
+        .collect::<Vec<_>>();
+
+    let mut variants = vec![];
+    for (i, (start, text)) in choices.iter().enumerate() {
+        match choices.get(i + 1) {
+            Some((end, _)) => {
+                for n in *start..(*end).min(start + 100) {
+                    variants.push((n.to_string(), text.clone()));
+                }
+            }
+            None => variants.push(("other".to_string(), text.clone())),
+        }
+    }
+
+    plural::select(variable, variants)
+}
+
+/// Splits a file stem such as `core_pt_BR` into its category and locale.
+/// Stems without a locale are the root bundle of the default locale.
+fn split_stem(stem: &str) -> (&str, Option<LanguageIdentifier>) {
+    for (i, _) in stem.match_indices('_') {
+        let (category, locale) = (&stem[..i], &stem[i + 1..]);
+        let language = locale.split('_').next().unwrap_or_default();
+        if !(2..=3).contains(&language.len()) || !language.chars().all(|x| x.is_ascii_lowercase()) {
+            continue;
+        }
+        if let Ok(locale) = LanguageIdentifier::from_str(&locale.replace('_', "-")) {
+            return (category, Some(locale));
+        }
+    }
+
+    (stem, None)
+}
+
+/// Loads every `.properties` file in the given directory.
+pub fn load_project_from_path(path: &Path, keys: &mut Keys) -> anyhow::Result<Project> {
+    let default_locale: LanguageIdentifier = locale!("en").id;
+    let mut project = Project {
+        default_locale: Some(default_locale.clone()),
+        ..Default::default()
+    };
+
+    let mut files = path
+        .read_dir()?
+        .filter_map(Result::ok)
+        .map(|x| x.path())
+        .filter(|x| x.extension().and_then(|x| x.to_str()) == Some("properties"))
+        .collect::<Vec<_>>();
+    // Root bundles first, so explicitly localized bundles take precedence,
+    // and descriptions are taken from the default locale where there are any.
+    files.sort_by_key(|x| {
+        let stem = x.file_stem().and_then(|x| x.to_str()).unwrap_or_default();
+        match split_stem(stem).1 {
+            None => 0,
+            Some(locale) if locale == default_locale => 1,
+            Some(_) => 2,
+        }
+    });
+
+    for file in files {
+        let stem = file.file_stem().and_then(|x| x.to_str()).unwrap();
+        let (category_id, locale) = split_stem(stem);
+        let locale = locale.unwrap_or_else(|| default_locale.clone());
+        let category_id = keys.category(category_id)?;
+
+        let category = project
+            .entry(category_id.clone())
+            .or_insert_with(|| Category {
+                key: category_id.clone(),
+                name: category_id.to_title_case(),
+                default_locale: default_locale.clone(),
+                descriptions: Default::default(),
+                notes: None,
+                sections: Default::default(),
+                translation_units: Default::default(),
+            });
+
+        for property in parse_properties(&std::fs::read_to_string(&file)?)? {
+            let (key, attr) = match property.key.split_once("__") {
+                Some((key, attr)) => (key, Some(attr)),
+                None => (property.key.as_str(), None),
+            };
+            let key = keys.unit(&category_id, key)?;
+
+            if let Some(description) = property.description.filter(|_| attr.is_none()) {
+                category
+                    .descriptions
+                    .entry(key.clone())
+                    .or_insert(description);
+            }
+
+            let value = to_pattern(&property.value);
+            let unit = category
+                .entry(locale.clone())
+                .or_insert_with(|| TranslationUnitMap::new(locale.clone()))
+                .entry(key.clone())
+                .or_insert_with(|| TranslationUnit::new(key.clone()));
+
+            match attr {
+                Some(attr) => {
+                    let attr = keys.attribute(&category_id, &key, attr)?;
+                    unit.attributes.insert(attr, value);
+                }
+                None => unit.main = value,
+            }
+        }
+    }
+
+    // Every category needs its base strings, even if it only showed up in
+    // translated bundles.
+    for category in project.values_mut() {
+        category
+            .entry(default_locale.clone())
+            .or_insert_with(|| TranslationUnitMap::new(default_locale.clone()));
+    }
+
+    Ok(project)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flt::pattern_source;
+
+    #[test]
+    fn parse_entries() {
+        let entries = parse_properties(concat!(
+            "# The greeting\n",
+            "welcome = Hello, {0}!\n",
+            "\n",
+            "long: one \\\n",
+            "    two\n",
+            "caf\\u00E9\\ key=\\u00E9t\\u00E9\n",
+        ))
+        .unwrap();
+
+        assert_eq!(
+            entries,
+            vec![
+                Property {
+                    key: "welcome".into(),
+                    value: "Hello, {0}!".into(),
+                    description: Some("The greeting".into()),
+                },
+                Property {
+                    key: "long".into(),
+                    value: "one two".into(),
+                    description: None,
+                },
+                Property {
+                    key: "café key".into(),
+                    value: "été".into(),
+                    description: None,
+                },
+            ]
+        );
+        assert_eq!(escape("café key", true), "caf\\u00E9\\ key");
+    }
+
+    #[test]
+    fn quoting_round_trip() {
+        let text = vec![render::Segment::Text("Don't {x}".into())];
+        let value = format_segments(&text, &mut vec![], false);
+        assert_eq!(value, "Don't {x}");
+        assert_eq!(to_pattern(&value), Pattern::text("Don't {x}"));
+
+        let mut segments = text.clone();
+        segments.push(render::Segment::Variable(
+            "name".into(),
+            render::VariableKind::String,
+        ));
+        let value = format_segments(&segments, &mut vec![], false);
+        assert_eq!(value, "Don''t '{'x'}'{0}");
+        assert_eq!(
+            pattern_source(&to_pattern(&value)),
+            "Don't { \"{\" }x{ \"}\" }{ $arg1 }"
+        );
+    }
+
+    #[test]
+    fn reordered_arguments_round_trip() {
+        let value = "{1} shared {0} files";
+        let key = TUIdentifier::try_from("shared").unwrap();
+        let mut units = TranslationUnitMap::new(locale!("en").id);
+        let mut unit = TranslationUnit::new(key.clone());
+        unit.main = to_pattern(value);
+        units.insert(key.clone(), unit);
+
+        let mut args = printf::arguments(&units, &key, None);
+        assert_eq!(
+            message_format("shared", &units[&key].main, &units, &mut args),
+            value
+        );
+    }
+
+    #[test]
+    fn choice_to_select() {
+        assert_eq!(
+            pattern_source(&to_pattern(
+                "{0,choice,0#No files|1#One file|1<{0} files from ''''{1}''''}"
+            )),
+            concat!(
+                "{ $arg1 ->\n",
+                "    [0] No files\n",
+                "    [1] One file\n",
+                "   *[other] { $arg1 } files from '{ $arg2 }'\n",
+                "}"
+            )
+        );
+    }
+}
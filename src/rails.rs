@@ -0,0 +1,569 @@
+//! Rails I18n locale files (`config/locales/<locale>.yml`), holding every
+//! category under a top-level locale key.
+//!
+//! Variables use `%{name}` interpolation, with dashes turned into underscores
+//! since Ruby I18n only matches word characters. Plural selects become hashes
+//! keyed by plural category, selecting on I18n's `count` option as in
+//! [`crate::i18next`], and a `[0]` variant becomes the `zero` key I18n
+//! prefers for a count of zero. Attributes are stored as
+//! `<message>__<attribute>` keys.
+//!
+//! Nested hashes are joined into dashed message ids, and ids are split at
+//! their dashes into nested hashes again on export, so lookups such as
+//! `t('core.users.show.title')` keep working. Where a prefix of an id is a
+//! message itself, or the nested keys would read back as a plural hash, the
+//! rest of the id is kept as one key.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+    str::FromStr,
+};
+
+use fluent_syntax::parser::ParserError;
+use heck::ToTitleCase;
+use icu::locid::LanguageIdentifier;
+use serde_yaml::Value;
+
+use crate::{
+    flt::render,
+    ir::{Category, Keys, Pattern, Project, TranslationUnit, TranslationUnitMap},
+    plural::{self, PluralSelect},
+    PathNode,
+};
+
+/// I18n always picks plural forms by the `count` option.
+const COUNT_VARIABLE: &str = "count";
+
+/// Keys that YAML 1.1, which Ruby's Psych implements, reads as something
+/// other than a string.
+const RESERVED_KEYS: &[&str] = &[
+    "y", "n", "yes", "no", "true", "false", "on", "off", "null", "~",
+];
+
+fn variable_name(name: &str) -> String {
+    name.replace('-', "_")
+}
+
+fn interpolate(segments: &[render::Segment], count: Option<&str>) -> String {
+    segments
+        .iter()
+        .map(|x| match x {
+            render::Segment::Text(text) => text.replace("%{", "%%{"),
+            render::Segment::Variable(name, _) if Some(name.as_str()) == count => {
+                format!("%{{{COUNT_VARIABLE}}}")
+            }
+            render::Segment::Variable(name, _) => format!("%{{{}}}", variable_name(name)),
+        })
+        .collect()
+}
+
+fn quote(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn key(value: &str) -> String {
+    let is_plain = value
+        .chars()
+        .next()
+        .is_some_and(|x| x.is_ascii_alphabetic() || x == '_')
+        && value
+            .chars()
+            .all(|x| x.is_ascii_alphanumeric() || x == '_' || x == '-')
+        && !RESERVED_KEYS.contains(&value.to_ascii_lowercase().as_str());
+
+    if is_plain {
+        value.to_string()
+    } else {
+        quote(value)
+    }
+}
+
+fn write_comment(out: &mut String, indent: &str, description: Option<&String>) {
+    if let Some(description) = description {
+        for line in description.lines() {
+            out.push_str(&format!("{indent}# {line}\n"));
+        }
+    }
+}
+
+fn write_value(
+    out: &mut String,
+    indent: &str,
+    name: &str,
+    pattern: &Pattern,
+    units: &TranslationUnitMap,
+) {
+    let Some(select) = PluralSelect::from_pattern(pattern) else {
+        let segments = render::segments(pattern, units);
+        out.push_str(&format!(
+            "{indent}{}: {}\n",
+            key(name),
+            quote(&interpolate(&segments, None))
+        ));
+        return;
+    };
+
+    let categories = plural::cardinal_categories(&units.locale);
+    out.push_str(&format!("{indent}{}:\n", key(name)));
+    if !categories.contains(&"zero") && select.numbers().any(|x| x.parse() == Ok(0)) {
+        let segments = render::segments(&select.expand_number(0, "zero"), units);
+        out.push_str(&format!(
+            "{indent}  zero: {}\n",
+            quote(&interpolate(&segments, Some(select.variable)))
+        ));
+    }
+    for category in categories {
+        let segments = render::segments(&select.expand(category), units);
+        out.push_str(&format!(
+            "{indent}  {category}: {}\n",
+            quote(&interpolate(&segments, Some(select.variable)))
+        ));
+    }
+}
+
+/// The values of a category as nested hashes, in the order they were
+/// inserted.
+#[derive(Default)]
+struct Tree<'a> {
+    entries: Vec<(String, Node<'a>)>,
+}
+
+enum Node<'a> {
+    Value {
+        description: Option<&'a String>,
+        pattern: &'a Pattern,
+    },
+    Hash(Tree<'a>),
+}
+
+impl<'a> Tree<'a> {
+    fn insert(&mut self, path: &[&str], node: Node<'a>) {
+        let (first, rest) = path.split_first().unwrap();
+        if rest.is_empty() {
+            self.entries.push((first.to_string(), node));
+            return;
+        }
+
+        let index = match self
+            .entries
+            .iter()
+            .position(|(key, x)| key == first && matches!(x, Node::Hash(_)))
+        {
+            Some(i) => i,
+            None => {
+                self.entries
+                    .push((first.to_string(), Node::Hash(Tree::default())));
+                self.entries.len() - 1
+            }
+        };
+        if let Node::Hash(tree) = &mut self.entries[index].1 {
+            tree.insert(rest, node);
+        }
+    }
+
+    /// Whether the hash would be read back as plurals rather than messages.
+    fn is_plural_hash(&self) -> bool {
+        self.entries
+            .iter()
+            .all(|(key, _)| plural::CATEGORIES.contains(&key.as_str()))
+    }
+
+    fn write(&self, out: &mut String, indent: &str, prefix: &str, units: &TranslationUnitMap) {
+        for (part, node) in self.entries.iter() {
+            let name = format!("{prefix}{part}");
+            match node {
+                Node::Value {
+                    description,
+                    pattern,
+                } => {
+                    write_comment(out, indent, *description);
+                    write_value(out, indent, &name, pattern, units);
+                }
+                Node::Hash(tree) if tree.is_plural_hash() => {
+                    tree.write(out, indent, &format!("{name}-"), units)
+                }
+                Node::Hash(tree) => {
+                    out.push_str(&format!("{indent}{}:\n", key(&name)));
+                    tree.write(out, &format!("{indent}  "), "", units);
+                }
+            }
+        }
+    }
+}
+
+/// Splits a value's name into the keys of its nested hashes. A prefix that
+/// names a value itself can't also be a hash, so the split stops there.
+fn split_name<'a>(name: &'a str, values: &BTreeSet<String>) -> Vec<&'a str> {
+    let mut path = vec![];
+    let mut rest = name;
+    let mut start = 0;
+
+    while let Some(i) = rest.find('-') {
+        let end = start + i;
+        if values.contains(&name[..end]) {
+            break;
+        }
+        path.push(&rest[..i]);
+        rest = &rest[i + 1..];
+        start = end + 1;
+    }
+
+    path.push(rest);
+    path
+}
+
+fn write_category(
+    out: &mut String,
+    category_id: &str,
+    category: &Category,
+    units: &TranslationUnitMap,
+) {
+    out.push_str(&format!("  {}:\n", key(category_id)));
+
+    let mut values = vec![];
+    for (key, unit) in units.iter() {
+        // Terms only exist to be inlined into messages
+        if unit.is_term() {
+            continue;
+        }
+
+        if category.base_strings().get(key).is_none() {
+            eprintln!(
+                "[{}] {} is not in the base strings; skipping",
+                units.locale, key
+            );
+            continue;
+        }
+
+        let mut description = category.descriptions.get(key);
+        if !unit.main.is_empty() || unit.attributes.is_empty() {
+            values.push((key.to_string(), description.take(), &unit.main));
+        }
+        for (attr, value) in unit.attributes.iter() {
+            values.push((format!("{key}__{attr}"), description.take(), value));
+        }
+    }
+
+    let names = values.iter().map(|(name, ..)| name.clone()).collect();
+    let mut tree = Tree::default();
+    for (name, description, pattern) in values.iter() {
+        tree.insert(
+            &split_name(name, &names),
+            Node::Value {
+                description: *description,
+                pattern,
+            },
+        );
+    }
+    tree.write(out, "    ", "", units);
+}
+
+pub fn generate(input: Project) -> Result<PathNode, ParserError> {
+    let mut locales: BTreeMap<String, String> = BTreeMap::new();
+
+    for (category_id, category) in input.categories.iter() {
+        for (locale, units) in category.translation_units.iter() {
+            let out = locales
+                .entry(locale.to_string())
+                .or_insert_with(|| format!("{}:\n", key(&locale.to_string())));
+            write_category(out, category_id, category, units);
+        }
+    }
+
+    Ok(PathNode::Directory(
+        locales
+            .into_iter()
+            .map(|(locale, out)| (format!("{locale}.yml"), PathNode::File(out.into_bytes())))
+            .collect(),
+    ))
+}
+
+/// Converts an interpolated string into a pattern.
+fn to_pattern(value: &str) -> Pattern {
+    let mut out = Pattern::default();
+    let mut rest = value;
+
+    while let Some(start) = rest.find('%') {
+        out.push_text(&rest[..start]);
+        let tail = &rest[start..];
+
+        if let Some(tail) = tail.strip_prefix("%%{") {
+            out.push_text("%{");
+            rest = tail;
+        } else if let Some((name, tail)) = tail
+            .strip_prefix("%{")
+            .and_then(|x| x.split_once('}'))
+            .filter(|(name, _)| name.chars().all(|x| x.is_alphanumeric() || x == '_'))
+        {
+            out.push_variable(name);
+            rest = tail;
+        } else {
+            out.push_text("%");
+            rest = &tail[1..];
+        }
+    }
+
+    out.push_text(rest);
+    out
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(x) => Some(x.clone()),
+        Value::Number(x) => Some(x.to_string()),
+        Value::Bool(x) => Some(x.to_string()),
+        _ => None,
+    }
+}
+
+/// The plural hash as a select on `count`, or `None` if the hash has keys
+/// other than plural categories. `zero` is I18n's key for a count of zero, so
+/// it becomes a `[0]` variant.
+fn plural_pattern(value: &serde_yaml::Mapping) -> Option<Pattern> {
+    let known = value
+        .keys()
+        .all(|x| x.as_str().is_some_and(|x| plural::CATEGORIES.contains(&x)));
+    if !known || value.is_empty() {
+        return None;
+    }
+
+    let mut variants = vec![];
+    for category in plural::CATEGORIES {
+        if let Some(x) = value.get(category) {
+            let key = if category == "zero" { "0" } else { category };
+            variants.push((key.to_string(), to_pattern(&scalar(x)?)));
+        }
+    }
+
+    Some(plural::select(COUNT_VARIABLE, variants))
+}
+
+/// Flattens a category's hash into message ids and patterns. Nested hashes
+/// other than plurals are joined into dashed ids.
+fn flatten(prefix: &str, value: &serde_yaml::Mapping, out: &mut Vec<(String, Pattern)>) {
+    for (key, value) in value.iter() {
+        let Some(key) = scalar(key) else {
+            continue;
+        };
+        let id = if prefix.is_empty() {
+            key
+        } else {
+            format!("{prefix}-{key}")
+        };
+
+        match value {
+            Value::Mapping(map) => match plural_pattern(map) {
+                Some(pattern) => out.push((id, pattern)),
+                None => flatten(&id, map, out),
+            },
+            x => match scalar(x) {
+                Some(x) => out.push((id, to_pattern(&x))),
+                None => eprintln!("{} is not a string; skipping", id),
+            },
+        }
+    }
+}
+
+/// Loads the locales of a locale file into the project.
+fn load_locales(
+    root: &serde_yaml::Mapping,
+    project: &mut Project,
+    default_locale: &LanguageIdentifier,
+    keys: &mut Keys,
+) -> anyhow::Result<()> {
+    for (locale, categories) in root.iter() {
+        let Some(locale) = scalar(locale) else {
+            continue;
+        };
+        let locale = LanguageIdentifier::from_str(&locale)?;
+        let Value::Mapping(categories) = categories else {
+            eprintln!("[{}] No categories found; skipping", locale);
+            continue;
+        };
+
+        for (category_id, messages) in categories.iter() {
+            let (Some(category_id), Value::Mapping(messages)) = (scalar(category_id), messages)
+            else {
+                eprintln!("[{}] Not a category; skipping", locale);
+                continue;
+            };
+            let category_id = keys.category(&category_id)?;
+
+            let category = project
+                .entry(category_id.clone())
+                .or_insert_with(|| Category {
+                    key: category_id.clone(),
+                    name: category_id.to_title_case(),
+                    default_locale: default_locale.clone(),
+                    descriptions: Default::default(),
+                    notes: None,
+                    sections: Default::default(),
+                    translation_units: Default::default(),
+                });
+            let units = category
+                .entry(locale.clone())
+                .or_insert_with(|| TranslationUnitMap::new(locale.clone()));
+
+            let mut values = vec![];
+            flatten("", messages, &mut values);
+
+            for (id, value) in values {
+                let (key, attr) = match id.split_once("__") {
+                    Some((key, attr)) => (key, Some(attr)),
+                    None => (id.as_str(), None),
+                };
+                let key = keys.unit(&category_id, key)?;
+                let unit = units
+                    .entry(key.clone())
+                    .or_insert_with(|| TranslationUnit::new(key.clone()));
+
+                match attr {
+                    Some(attr) => {
+                        let attr = keys.attribute(&category_id, &key, attr)?;
+                        unit.attributes.insert(attr, value);
+                    }
+                    None => unit.main = value,
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Loads every `.yml` or `.yaml` locale file below the given directory. The
+/// files don't say which locale is the app's default, so it is passed in.
+pub fn load_project_from_path(
+    path: &Path,
+    default_locale: &LanguageIdentifier,
+    keys: &mut Keys,
+) -> anyhow::Result<Project> {
+    let mut project = Project {
+        default_locale: Some(default_locale.clone()),
+        ..Default::default()
+    };
+
+    let files = walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .map(|x| x.into_path())
+        .filter(|x| matches!(x.extension().and_then(|x| x.to_str()), Some("yml" | "yaml")));
+
+    for file in files {
+        let root: Value = serde_yaml::from_str(&std::fs::read_to_string(&file)?)?;
+        let Value::Mapping(root) = root else {
+            eprintln!("[{}] Not a locale file; skipping", file.display());
+            continue;
+        };
+        load_locales(&root, &mut project, default_locale, keys)?;
+    }
+
+    // Every category needs its base strings, even if it only showed up in
+    // translated files.
+    for category in project.values_mut() {
+        category
+            .entry(default_locale.clone())
+            .or_insert_with(|| TranslationUnitMap::new(default_locale.clone()));
+    }
+
+    Ok(project)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flt::pattern_source;
+    use icu::locid::locale;
+
+    #[test]
+    fn nested_hashes_round_trip() {
+        let yaml = concat!(
+            "en:\n",
+            "  admin:\n",
+            "    title: \"Admin\"\n",
+            "    title-short: \"Adm\"\n",
+            "    users:\n",
+            "      files:\n",
+            "        one: \"One file\"\n",
+            "        other: \"%{count} files\"\n",
+            "      show:\n",
+            "        title: \"User %{name}\"\n",
+        );
+        let Value::Mapping(root) = serde_yaml::from_str(yaml).unwrap() else {
+            unreachable!()
+        };
+        let mut project = Project::default();
+        load_locales(&root, &mut project, &locale!("en").id, &mut Keys::strict()).unwrap();
+
+        let category = project.values().next().unwrap();
+        let base = category.base_strings();
+        assert!(base.keys().any(|x| &**x == "users-show-title"));
+
+        let mut out = String::from("en:\n");
+        write_category(&mut out, "admin", category, base);
+        assert_eq!(out, yaml);
+    }
+
+    #[test]
+    fn message_hashes_are_not_plurals() {
+        let mut names = BTreeSet::new();
+        names.insert("sizes-few".to_string());
+        names.insert("sizes-many".to_string());
+        let mut tree = Tree::default();
+        let pattern = Pattern::text("x");
+        for name in names.iter() {
+            tree.insert(
+                &split_name(name, &names),
+                Node::Value {
+                    description: None,
+                    pattern: &pattern,
+                },
+            );
+        }
+
+        let mut out = String::new();
+        tree.write(&mut out, "", "", &TranslationUnitMap::new(locale!("en").id));
+        assert_eq!(out, "sizes-few: \"x\"\nsizes-many: \"x\"\n");
+        assert_eq!(
+            split_name("title-short", &["title".to_string()].into()),
+            vec!["title-short"]
+        );
+    }
+
+    #[test]
+    fn plural_hash_to_select() {
+        let value: Value = serde_yaml::from_str(concat!(
+            "zero: \"No files\"\n",
+            "one: \"One file from %{user}\"\n",
+            "other: \"%{count} files, 100%%{done}\"\n",
+        ))
+        .unwrap();
+        let Value::Mapping(map) = value else {
+            unreachable!()
+        };
+
+        assert_eq!(
+            pattern_source(&plural_pattern(&map).unwrap()),
+            concat!(
+                "{ $count ->\n",
+                "    [0] No files\n",
+                "    [one] One file from { $user }\n",
+                "   *[other] { $count } files, 100%{ \"{\" }done{ \"}\" }\n",
+                "}",
+            )
+        );
+    }
+}
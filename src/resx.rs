@@ -0,0 +1,186 @@
+//! .NET XML resource files, `<Category>.resx` for the default locale and
+//! `<Category>.<locale>.resx` for every other.
+//!
+//! Values are composite format strings taking positional arguments (`{0}`),
+//! ordered by the base strings the same way as [`crate::printf`]. As with
+//! printf-style strings, braces are only escaped in values that take
+//! arguments. Resources have no notion of plurals, so plural selects are
+//! written as their default variant. Attributes are stored as
+//! `<message>__<attribute>` names.
+
+use std::collections::BTreeMap;
+
+use fluent_syntax::parser::ParserError;
+use heck::ToPascalCase;
+use html_escape::{encode_double_quoted_attribute, encode_text};
+
+use crate::{
+    flt::render,
+    ir::{Project, TUIdentifier},
+    printf, PathNode,
+};
+
+const HEADER: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<root>
+  <resheader name="resmimetype">
+    <value>text/microsoft-resx</value>
+  </resheader>
+  <resheader name="version">
+    <value>2.0</value>
+  </resheader>
+  <resheader name="reader">
+    <value>System.Resources.ResXResourceReader, System.Windows.Forms, Version=4.0.0.0, Culture=neutral, PublicKeyToken=b77a5c561934e089</value>
+  </resheader>
+  <resheader name="writer">
+    <value>System.Resources.ResXResourceWriter, System.Windows.Forms, Version=4.0.0.0, Culture=neutral, PublicKeyToken=b77a5c561934e089</value>
+  </resheader>
+"#;
+
+fn resource_name(key: &TUIdentifier, attr: Option<&TUIdentifier>) -> String {
+    match attr {
+        Some(attr) => format!("{}__{}", key, attr),
+        None => key.to_string(),
+    }
+}
+
+fn format_segments(segments: &[render::Segment], args: &mut Vec<String>) -> String {
+    let formatted = segments
+        .iter()
+        .any(|x| matches!(x, render::Segment::Variable(..)));
+
+    segments
+        .iter()
+        .map(|x| match x {
+            render::Segment::Text(text) if formatted => text.replace('{', "{{").replace('}', "}}"),
+            render::Segment::Text(text) => text.clone(),
+            render::Segment::Variable(name, _) => {
+                format!("{{{}}}", render::argument_index(args, name))
+            }
+        })
+        .collect()
+}
+
+fn write_data(out: &mut String, name: &str, value: &str, comment: Option<&String>) {
+    out.push_str(&format!(
+        "  <data name=\"{}\" xml:space=\"preserve\">\n    <value>{}</value>\n",
+        encode_double_quoted_attribute(name),
+        encode_text(value)
+    ));
+    if let Some(comment) = comment {
+        out.push_str(&format!(
+            "    <comment>{}</comment>\n",
+            encode_text(comment)
+        ));
+    }
+    out.push_str("  </data>\n");
+}
+
+pub fn generate(input: Project) -> Result<PathNode, ParserError> {
+    let mut files = BTreeMap::new();
+
+    for (category_id, category) in input.categories.iter() {
+        let base = category.base_strings();
+
+        for locale in category.ordered_locale_keys() {
+            let units = category.get(locale).unwrap();
+            let mut out = String::from(HEADER);
+
+            for (key, attr) in category.ordered_tu_identity_keys() {
+                // Terms only exist to be inlined into messages
+                if base[key].is_term() {
+                    continue;
+                }
+
+                let Some(unit) = units.get(key) else {
+                    continue;
+                };
+                let Some(value) = unit.value(attr) else {
+                    continue;
+                };
+                if attr.is_none() && value.is_empty() && !unit.attributes.is_empty() {
+                    continue;
+                }
+
+                let mut args = printf::arguments(base, key, attr);
+                let segments = render::segments(value, units);
+                write_data(
+                    &mut out,
+                    &resource_name(key, attr),
+                    &format_segments(&segments, &mut args),
+                    category.descriptions.get(key),
+                );
+            }
+
+            out.push_str("</root>\n");
+
+            let name = if *locale == category.default_locale {
+                format!("{}.resx", category_id.to_pascal_case())
+            } else {
+                format!("{}.{}.resx", category_id.to_pascal_case(), locale)
+            };
+            files.insert(name, PathNode::File(out.into_bytes()));
+        }
+    }
+
+    Ok(PathNode::Directory(files))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flt::test_project;
+
+    #[test]
+    fn braces_and_arguments() {
+        let project = test_project(&[
+            (
+                "en",
+                concat!(
+                    "# Shown in the header\n",
+                    "plain = Use {\"{\"}braces{\"}\"}\n",
+                    "login = { $user } has {\"{\"}{ $count }{\"}\"} items\n",
+                    "    .title = Log in\n",
+                ),
+            ),
+            (
+                "de",
+                "login = {\"{\"}{ $count }{\"}\"} Dinge für { $user }\n",
+            ),
+        ]);
+        let tree = generate(project).unwrap();
+
+        assert_eq!(
+            tree.text("Core.resx"),
+            format!(
+                concat!(
+                    "{}",
+                    "  <data name=\"login\" xml:space=\"preserve\">\n",
+                    "    <value>{{0}} has {{{{{{1}}}}}} items</value>\n",
+                    "  </data>\n",
+                    "  <data name=\"login__title\" xml:space=\"preserve\">\n",
+                    "    <value>Log in</value>\n",
+                    "  </data>\n",
+                    "  <data name=\"plain\" xml:space=\"preserve\">\n",
+                    "    <value>Use {{braces}}</value>\n",
+                    "    <comment>Shown in the header</comment>\n",
+                    "  </data>\n",
+                    "</root>\n",
+                ),
+                HEADER
+            )
+        );
+        assert_eq!(
+            tree.text("Core.de.resx"),
+            format!(
+                concat!(
+                    "{}",
+                    "  <data name=\"login\" xml:space=\"preserve\">\n",
+                    "    <value>{{{{{{1}}}}}} Dinge für {{0}}</value>\n",
+                    "  </data>\n",
+                    "</root>\n",
+                ),
+                HEADER
+            )
+        );
+    }
+}
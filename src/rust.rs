@@ -0,0 +1,343 @@
+//! Rust modules with typed accessors, backed by `fluent-bundle`.
+//!
+//! Each category becomes a module embedding its FTL resources, the same way
+//! [`crate::ts`] inlines them, with a struct exposing a method per message.
+//! `mod.rs` holds the shared bundle handling and a `Strings` struct with
+//! every category. The generated code depends on the `fluent-bundle` and
+//! `unic-langid` crates.
+
+use std::collections::BTreeMap;
+
+use fluent_syntax::parser::ParserError;
+use heck::{ToPascalCase, ToShoutySnakeCase, ToSnakeCase};
+
+use crate::{
+    codegen::{self, Accessor},
+    flt::render::VariableKind,
+    ir::{CIdentifier, Category, Project},
+    PathNode,
+};
+
+const HEADER: &str = "// Generated by stringly. Do not edit.\n";
+
+const RUNTIME: &str = r#"use fluent_bundle::{FluentArgs, FluentBundle, FluentResource};
+use unic_langid::LanguageIdentifier;
+
+/// The FTL resources of a category, keyed by locale.
+pub type Resources = &'static [(&'static str, &'static str)];
+
+/// A category's bundles for one locale, falling back to its default locale.
+pub struct Bundles {
+    bundles: Vec<FluentBundle<FluentResource>>,
+}
+
+impl Bundles {
+    /// `shared` resources, such as those of `core`, are added first so that
+    /// their terms can be referenced.
+    pub fn new(locale: &str, default_locale: &str, resources: Resources, shared: &[Resources]) -> Self {
+        let mut locales = vec![locale];
+        if locale != default_locale {
+            locales.push(default_locale);
+        }
+
+        let bundles = locales
+            .into_iter()
+            .filter_map(|locale| {
+                let (locale, source) = find(resources, locale)?;
+                let langid: LanguageIdentifier = locale.parse().ok()?;
+                let mut bundle = FluentBundle::new(vec![langid]);
+                bundle.set_use_isolating(false);
+                for shared in shared {
+                    if let Some((_, source)) = find(shared, locale) {
+                        bundle.add_resource_overriding(parse(source));
+                    }
+                }
+                bundle.add_resource_overriding(parse(source));
+                Some(bundle)
+            })
+            .collect();
+
+        Self { bundles }
+    }
+
+    pub fn format(&self, id: &str, attr: Option<&str>, args: Option<&FluentArgs>) -> String {
+        for bundle in self.bundles.iter() {
+            let Some(message) = bundle.get_message(id) else {
+                continue;
+            };
+            let pattern = match attr {
+                Some(attr) => message.get_attribute(attr).map(|x| x.value()),
+                None => message.value(),
+            };
+            let Some(pattern) = pattern else {
+                continue;
+            };
+            let mut errors = vec![];
+            return bundle.format_pattern(pattern, args, &mut errors).into_owned();
+        }
+
+        match attr {
+            Some(attr) => format!("{id}.{attr}"),
+            None => id.to_string(),
+        }
+    }
+}
+
+/// Finds the resource for a locale, falling back to one of the same language.
+fn find(resources: Resources, locale: &str) -> Option<(&'static str, &'static str)> {
+    resources
+        .iter()
+        .copied()
+        .find(|(x, _)| *x == locale)
+        .or_else(|| {
+            let language = locale.split(['-', '_']).next()?;
+            resources
+                .iter()
+                .copied()
+                .find(|(x, _)| x.split('-').next() == Some(language))
+        })
+}
+
+fn parse(source: &'static str) -> FluentResource {
+    FluentResource::try_new(source.to_string()).unwrap_or_else(|(resource, _)| resource)
+}
+"#;
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "do", "dyn", "else",
+    "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop", "macro",
+    "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "self", "static",
+    "struct", "super", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
+fn ident(value: &str) -> String {
+    let value = value.to_snake_case();
+    if KEYWORDS.contains(&value.as_str()) {
+        format!("r#{value}")
+    } else {
+        value
+    }
+}
+
+/// A raw string literal with enough `#`s to hold the value.
+fn raw_string(value: &str) -> String {
+    let mut hashes = 1;
+    while value.contains(&format!("\"{}", "#".repeat(hashes))) {
+        hashes += 1;
+    }
+    let hashes = "#".repeat(hashes);
+    format!("r{hashes}\"{value}\"{hashes}")
+}
+
+fn generate_accessor(accessor: &Accessor) -> String {
+    let mut out = String::new();
+
+    if let Some(description) = accessor.description.as_ref() {
+        for line in description.lines() {
+            out.push_str(&format!("    /// {line}\n"));
+        }
+    }
+
+    let attr = match accessor.attr.as_ref() {
+        Some(attr) => format!("Some({:?})", &**attr),
+        None => "None".to_string(),
+    };
+
+    if accessor.variables.is_empty() {
+        out.push_str(&format!(
+            "    pub fn {}(&self) -> String {{\n        self.bundles.format({:?}, {attr}, None)\n    }}\n",
+            ident(&accessor.name()),
+            &*accessor.id
+        ));
+        return out;
+    }
+
+    let params = accessor
+        .variables
+        .iter()
+        .map(|(name, kind)| {
+            let ty = match kind {
+                VariableKind::String => "&str",
+                VariableKind::Number => "impl Into<FluentNumber>",
+            };
+            format!("{}: {ty}", ident(name))
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    out.push_str(&format!(
+        "    pub fn {}(&self, {params}) -> String {{\n        let mut args = FluentArgs::new();\n",
+        ident(&accessor.name())
+    ));
+    for (name, kind) in accessor.variables.iter() {
+        let value = match kind {
+            VariableKind::String => ident(name),
+            VariableKind::Number => format!("Into::<FluentNumber>::into({})", ident(name)),
+        };
+        out.push_str(&format!("        args.set({name:?}, {value});\n"));
+    }
+    out.push_str(&format!(
+        "        self.bundles.format({:?}, {attr}, Some(&args))\n    }}\n",
+        &*accessor.id
+    ));
+
+    out
+}
+
+fn generate_module(category_id: &CIdentifier, category: &Category, has_core: bool) -> String {
+    let mut out = format!(
+        "{HEADER}\n#[allow(unused_imports)]\nuse fluent_bundle::{{types::FluentNumber, FluentArgs}};\n\nuse super::{{Bundles, Resources}};\n\n"
+    );
+
+    for (locale, units) in category.translation_units.iter() {
+        let resource = units.to_flt_resource(category);
+        out.push_str(&format!(
+            "const {}: &str = {};\n\n",
+            locale.to_string().to_shouty_snake_case(),
+            raw_string(&fluent_syntax::serializer::serialize(&resource))
+        ));
+    }
+
+    out.push_str(&format!(
+        "pub const DEFAULT_LOCALE: &str = {:?};\n\npub const RESOURCES: Resources = &[{}];\n\n",
+        category.default_locale.to_string(),
+        category
+            .translation_units
+            .keys()
+            .map(|x| format!(
+                "({:?}, {})",
+                x.to_string(),
+                x.to_string().to_shouty_snake_case()
+            ))
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+
+    let name = category_id.to_pascal_case();
+    let shared = if has_core && &**category_id != "core" {
+        "&[super::core::RESOURCES]"
+    } else {
+        "&[]"
+    };
+    out.push_str(&format!(
+        "pub struct {name} {{\n    bundles: Bundles,\n}}\n\nimpl {name} {{\n    pub fn new(locale: &str) -> Self {{\n        Self {{\n            bundles: Bundles::new(locale, DEFAULT_LOCALE, RESOURCES, {shared}),\n        }}\n    }}\n"
+    ));
+
+    // Snake case can give different messages the same method name, such as
+    // `welcome.title` and `welcome_title`
+    let mut methods: BTreeMap<String, String> = BTreeMap::new();
+    for accessor in codegen::accessors(category) {
+        let source = match accessor.attr.as_ref() {
+            Some(attr) => format!("{}.{}", accessor.id, attr),
+            None => accessor.id.to_string(),
+        };
+        let method = ident(&accessor.name());
+        if let Some(other) = methods.get(&method) {
+            eprintln!(
+                "[{}] {} and {} are both `{}`; skipping {}",
+                category_id, other, source, method, source
+            );
+            continue;
+        }
+        methods.insert(method, source);
+
+        out.push('\n');
+        out.push_str(&generate_accessor(&accessor));
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+fn generate_index(input: &Project) -> String {
+    let mut out = format!("{HEADER}\n");
+
+    for category_id in input.categories.keys() {
+        out.push_str(&format!("pub mod {};\n", ident(category_id)));
+    }
+    out.push('\n');
+    out.push_str(RUNTIME);
+
+    out.push_str("\npub struct Strings {\n");
+    for category_id in input.categories.keys() {
+        out.push_str(&format!(
+            "    pub {}: self::{}::{},\n",
+            ident(category_id),
+            ident(category_id),
+            category_id.to_pascal_case()
+        ));
+    }
+    out.push_str("}\n\nimpl Strings {\n    pub fn new(locale: &str) -> Self {\n        Self {\n");
+    for category_id in input.categories.keys() {
+        out.push_str(&format!(
+            "            {}: self::{}::{}::new(locale),\n",
+            ident(category_id),
+            ident(category_id),
+            category_id.to_pascal_case()
+        ));
+    }
+    out.push_str("        }\n    }\n}\n");
+
+    out
+}
+
+pub fn generate(input: Project) -> Result<PathNode, ParserError> {
+    let mut files = BTreeMap::new();
+    let has_core = input.categories.keys().any(|x| &**x == "core");
+
+    for (category_id, category) in input.categories.iter() {
+        files.insert(
+            format!("{}.rs", category_id.to_snake_case()),
+            PathNode::File(generate_module(category_id, category, has_core).into_bytes()),
+        );
+    }
+
+    files.insert(
+        "mod.rs".into(),
+        PathNode::File(generate_index(&input).into_bytes()),
+    );
+
+    Ok(PathNode::Directory(files))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flt::test_project;
+
+    #[test]
+    fn accessor_names() {
+        let project = test_project(&[(
+            "en",
+            concat!(
+                "welcome = Welcome, { $user-name }\n",
+                "    .title = Welcome\n",
+                "welcome_title = Hello\n",
+                "type = Kind\n",
+            ),
+        )]);
+        let tree = generate(project).unwrap();
+        let module = tree.text("core.rs");
+        let methods = module
+            .lines()
+            .filter_map(|x| x.trim().strip_prefix("pub fn "))
+            .collect::<Vec<_>>();
+
+        // `welcome_title` would shadow the attribute's accessor
+        assert_eq!(
+            methods,
+            vec![
+                "new(locale: &str) -> Self {",
+                "r#type(&self) -> String {",
+                "welcome(&self, user_name: &str) -> String {",
+                "welcome_title(&self) -> String {",
+            ]
+        );
+        assert!(module.contains("        args.set(\"user-name\", user_name);\n"));
+        assert!(
+            module.contains("        self.bundles.format(\"welcome\", Some(\"title\"), None)\n")
+        );
+        assert!(!module.contains("\"welcome_title\""));
+    }
+}
@@ -0,0 +1,213 @@
+//! Swift accessors over the String Catalogs written by [`crate::xcstrings`].
+//!
+//! `Strings.swift` holds a `Strings` enum with a nested enum per category,
+//! which has a static property per message without variables and a static
+//! function per message with them. Lookups go through `String(localized:)`
+//! against the category's table in the package's bundle, so the catalogs are
+//! written alongside it.
+
+use fluent_syntax::parser::ParserError;
+use heck::{ToLowerCamelCase, ToPascalCase};
+
+use crate::{
+    codegen::{self, Accessor},
+    flt::render,
+    ir::{Category, Project},
+    xcstrings, PathNode,
+};
+
+const HEADER: &str = "// Generated by stringly. Do not edit.\n";
+
+const KEYWORDS: &[&str] = &[
+    "as",
+    "associatedtype",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "continue",
+    "default",
+    "defer",
+    "deinit",
+    "do",
+    "else",
+    "enum",
+    "extension",
+    "fallthrough",
+    "false",
+    "fileprivate",
+    "for",
+    "func",
+    "guard",
+    "if",
+    "import",
+    "in",
+    "init",
+    "inout",
+    "internal",
+    "is",
+    "let",
+    "nil",
+    "open",
+    "operator",
+    "private",
+    "protocol",
+    "public",
+    "repeat",
+    "rethrows",
+    "return",
+    "self",
+    "static",
+    "struct",
+    "subscript",
+    "super",
+    "switch",
+    "throw",
+    "throws",
+    "true",
+    "try",
+    "typealias",
+    "var",
+    "where",
+    "while",
+];
+
+fn ident(value: &str) -> String {
+    let value = value.to_lower_camel_case();
+    if KEYWORDS.contains(&value.as_str()) {
+        format!("`{value}`")
+    } else {
+        value
+    }
+}
+
+fn generate_accessor(table: &str, accessor: &Accessor) -> String {
+    let mut out = String::new();
+    if let Some(description) = accessor.description.as_ref() {
+        for line in description.lines() {
+            out.push_str(&format!("        /// {line}\n"));
+        }
+    }
+
+    let lookup = format!(
+        "String(localized: {:?}, table: {table:?}, bundle: .module)",
+        accessor.name()
+    );
+
+    if accessor.variables.is_empty() {
+        out.push_str(&format!(
+            "        public static var {}: String {{\n            {lookup}\n        }}\n",
+            ident(&accessor.name())
+        ));
+        return out;
+    }
+
+    let params = accessor
+        .variables
+        .iter()
+        .map(|(name, kind)| {
+            let ty = match kind {
+                render::VariableKind::String => "String",
+                render::VariableKind::Number => "Int",
+            };
+            format!("{}: {ty}", ident(name))
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let args = accessor
+        .variables
+        .iter()
+        .map(|(name, _)| format!(", {}", ident(name)))
+        .collect::<String>();
+
+    out.push_str(&format!(
+        "        public static func {}({params}) -> String {{\n            String.localizedStringWithFormat({lookup}{args})\n        }}\n",
+        ident(&accessor.name())
+    ));
+
+    out
+}
+
+fn generate_enum(category: &Category) -> String {
+    let table = category.key.to_pascal_case();
+    let mut out = format!("    /// {}\n    public enum {table} {{\n", category.name);
+
+    for (i, accessor) in codegen::accessors(category).iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        out.push_str(&generate_accessor(&table, accessor));
+    }
+
+    out.push_str("    }\n");
+    out
+}
+
+fn generate_source(input: &Project) -> String {
+    let mut out = format!("{HEADER}\nimport Foundation\n\npublic enum Strings {{\n");
+
+    for (i, category) in input.categories.values().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        out.push_str(&generate_enum(category));
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+pub fn generate(input: Project) -> Result<PathNode, ParserError> {
+    let source = generate_source(&input);
+
+    let PathNode::Directory(mut files) = xcstrings::generate(input)? else {
+        unreachable!("catalogs are generated as a directory");
+    };
+    files.insert("Strings.swift".into(), PathNode::File(source.into_bytes()));
+
+    Ok(PathNode::Directory(files))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flt::test_project;
+
+    #[test]
+    fn accessors_over_catalogs() {
+        let project = test_project(&[(
+            "en",
+            concat!(
+                "# The default case\n",
+                "default = Default\n",
+                "files = { $count ->\n",
+                "    [one] One file in { $folder }\n",
+                "   *[other] { $count } files in { $folder }\n",
+                "}\n",
+            ),
+        )]);
+        let tree = generate(project).unwrap();
+
+        assert!(matches!(tree, PathNode::Directory(ref x) if x.contains_key("Core.xcstrings")));
+        assert_eq!(
+            tree.text("Strings.swift"),
+            concat!(
+                "// Generated by stringly. Do not edit.\n\n",
+                "import Foundation\n\n",
+                "public enum Strings {\n",
+                "    /// Core\n",
+                "    public enum Core {\n",
+                "        /// The default case\n",
+                "        public static var `default`: String {\n",
+                "            String(localized: \"default\", table: \"Core\", bundle: .module)\n",
+                "        }\n\n",
+                "        public static func files(count: Int, folder: String) -> String {\n",
+                "            String.localizedStringWithFormat(",
+                "String(localized: \"files\", table: \"Core\", bundle: .module), count, folder)\n",
+                "        }\n",
+                "    }\n",
+                "}\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,166 @@
+//! A TMX 1.4 translation memory, pairing every unit of the base strings with
+//! its translations.
+//!
+//! Each translation unit records its category and key as `<prop>` metadata.
+//! Placeables and the structure of select expressions are kept as `<ph>`
+//! native codes, tokenized the same way as [`crate::xliff`], so that only the
+//! text is matched against the memory.
+
+use fluent_syntax::parser::ParserError;
+use html_escape::{encode_double_quoted_attribute, encode_text};
+
+use crate::{
+    ir::{Category, Project},
+    xliff::{self, Token},
+    PathNode,
+};
+
+/// Writes tokens as segment content. `codes` holds the native codes of the
+/// whole unit with their `x`, so that a code has the same `x` in every
+/// language; a code used twice in one segment gets a second one.
+fn write_segment(tokens: &[Token], codes: &mut Vec<(String, usize)>) -> String {
+    let mut used = vec![];
+    let mut out = String::new();
+
+    for token in tokens {
+        match token {
+            Token::Text(text) => out.push_str(&encode_text(text)),
+            Token::Code(code) | Token::Select(code) => {
+                let x = match codes.iter().find(|(c, x)| c == code && !used.contains(x)) {
+                    Some((_, x)) => *x,
+                    None => {
+                        codes.push((code.clone(), codes.len() + 1));
+                        codes.len()
+                    }
+                };
+                used.push(x);
+                out.push_str(&format!("<ph x=\"{x}\">{}</ph>", encode_text(code)));
+            }
+        }
+    }
+
+    out
+}
+
+fn write_category(out: &mut String, category: &Category) {
+    let base = category.base_strings();
+
+    for (key, attr) in category.ordered_tu_identity_keys() {
+        let Some(source) = base.get(key).and_then(|x| x.value(attr)) else {
+            continue;
+        };
+        if attr.is_none() && source.is_empty() {
+            continue;
+        }
+
+        let mut codes = vec![];
+        let mut variants = vec![];
+        for locale in category.ordered_locale_keys() {
+            let Some(value) = category
+                .get(locale)
+                .and_then(|x| x.get(key))
+                .and_then(|x| x.value(attr))
+            else {
+                continue;
+            };
+
+            let mut tokens = vec![];
+            xliff::tokenize(value, &mut tokens);
+            variants.push((locale, write_segment(&tokens, &mut codes)));
+        }
+
+        // Only the base string, with nothing to pair it with
+        if variants.len() < 2 {
+            continue;
+        }
+
+        let unit_id = xliff::unit_id(key, attr);
+        out.push_str(&format!(
+            "    <tu tuid=\"{}\" srclang=\"{}\">\n",
+            encode_double_quoted_attribute(&format!("{}.{}", category.key, unit_id)),
+            base.locale
+        ));
+        out.push_str(&format!(
+            "      <prop type=\"x-category\">{}</prop>\n",
+            encode_text(&category.key.to_string())
+        ));
+        out.push_str(&format!(
+            "      <prop type=\"x-key\">{}</prop>\n",
+            encode_text(&unit_id)
+        ));
+        if let Some(description) = category.descriptions.get(key) {
+            out.push_str(&format!(
+                "      <note>{}</note>\n",
+                encode_text(description)
+            ));
+        }
+        for (locale, segment) in variants {
+            out.push_str(&format!(
+                "      <tuv xml:lang=\"{locale}\">\n        <seg>{segment}</seg>\n      </tuv>\n"
+            ));
+        }
+        out.push_str("    </tu>\n");
+    }
+}
+
+pub fn generate(input: Project) -> Result<PathNode, ParserError> {
+    // Categories may each have their own source language
+    let srclang = match &input.default_locale {
+        Some(x) => x.to_string(),
+        None => "*all*".to_string(),
+    };
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<tmx version=\"1.4\">\n");
+    out.push_str(&format!(
+        concat!(
+            "  <header creationtool=\"stringly\" creationtoolversion=\"{}\"",
+            " segtype=\"block\" o-tmf=\"stringly\" adminlang=\"en\"",
+            " srclang=\"{}\" datatype=\"plaintext\">\n",
+            "    <prop type=\"x-project\">{}</prop>\n",
+            "  </header>\n",
+        ),
+        env!("CARGO_PKG_VERSION"),
+        srclang,
+        encode_text(&input.name)
+    ));
+    out.push_str("  <body>\n");
+    for category in input.categories.values() {
+        write_category(&mut out, category);
+    }
+    out.push_str("  </body>\n</tmx>\n");
+
+    Ok(PathNode::File(out.into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flt::test_project;
+
+    #[test]
+    fn codes_match_across_languages() {
+        let project = test_project(&[
+            ("en", "move = Move { $file } from { $from } to { $to }\n"),
+            (
+                "de",
+                "move = Nach { $to } von { $from }: { $file }, { $file }\n",
+            ),
+        ]);
+        let PathNode::File(tmx) = generate(project).unwrap() else {
+            panic!("a TMX is a single file");
+        };
+        let tmx = String::from_utf8(tmx).unwrap();
+
+        assert!(tmx.contains(concat!(
+            "      <tuv xml:lang=\"en\">\n",
+            "        <seg>Move <ph x=\"1\">{ $file }</ph> from <ph x=\"2\">{ $from }</ph> ",
+            "to <ph x=\"3\">{ $to }</ph></seg>\n",
+            "      </tuv>\n",
+            "      <tuv xml:lang=\"de\">\n",
+            "        <seg>Nach <ph x=\"3\">{ $to }</ph> von <ph x=\"2\">{ $from }</ph>: ",
+            "<ph x=\"1\">{ $file }</ph>, <ph x=\"4\">{ $file }</ph></seg>\n",
+            "      </tuv>\n",
+        )));
+    }
+}
@@ -1,4 +1,4 @@
-use std::sync::OnceLock;
+use std::{sync::OnceLock, time::SystemTime};
 
 use html_escape::decode_html_entities;
 use icu::locid::LanguageIdentifier;
@@ -6,10 +6,16 @@ use regex::{Captures, Regex};
 use serde::Deserialize;
 use serde_json::json;
 
-use crate::ir::{Project, TUIdentifier, TranslationUnit, TranslationUnitMap};
+use crate::{
+    flt::{parse_pattern_lossy, pattern_source},
+    ir::{metadata, Metadata, Project, Status, TUIdentifier, TranslationUnit, TranslationUnitMap},
+};
 
 const GOOGLE_TRANSLATE_URL: &str = "https://translation.googleapis.com/language/translate/v2";
 
+/// Recorded as the translator of machine translated units.
+const TRANSLATOR: &str = "Google Translate";
+
 #[derive(Debug, Clone, Deserialize)]
 struct TranslateResponse {
     data: TranslateData,
@@ -112,13 +118,13 @@ pub async fn process(
             .translation_units
             .iter()
             .flat_map(|(key, x)| {
-                let source = convert_to_html(&x.main);
+                let source = convert_to_html(&pattern_source(&x.main));
                 std::iter::once(KeyedString {
                     key: key.to_string(),
                     value: source,
                 })
                 .chain(x.attributes.iter().map(move |x| {
-                    let source = convert_to_html(x.1);
+                    let source = convert_to_html(&pattern_source(x.1));
 
                     KeyedString {
                         key: format!("{key}__{}", x.0),
@@ -137,6 +143,8 @@ pub async fn process(
         };
 
         eprintln!("Generating translation units...");
+        let base = v.base_strings();
+        let modified = metadata::timestamp(SystemTime::now());
         for x in strings.into_iter() {
             let mut iter = x.key.split("__");
             let base_id = TUIdentifier::try_from(iter.next().unwrap()).unwrap();
@@ -144,12 +152,19 @@ pub async fn process(
 
             if let Some(meta_id) = meta_id {
                 let map = out.translation_units.get_mut(&base_id).unwrap();
-                map.attributes.insert(meta_id, convert_from_html(&x.target));
+                map.attributes
+                    .insert(meta_id, parse_pattern_lossy(&convert_from_html(&x.target)));
             } else {
                 out.translation_units.insert(TranslationUnit {
-                    key: base_id.clone(),
-                    main: convert_from_html(&x.target),
-                    attributes: Default::default(),
+                    kind: base[&base_id].kind,
+                    main: parse_pattern_lossy(&convert_from_html(&x.target)),
+                    metadata: Metadata {
+                        status: Some(Status::MachineTranslated),
+                        modified: Some(modified.clone()),
+                        translator: Some(TRANSLATOR.to_string()),
+                        source_hash: Some(metadata::source_hash(&base[&base_id])),
+                    },
+                    ..TranslationUnit::new(base_id.clone())
                 });
             }
         }
@@ -4,7 +4,7 @@ use fluent_syntax::parser::ParserError;
 use heck::{ToLowerCamelCase, ToPascalCase, ToShoutySnakeCase};
 use icu::locid::LanguageIdentifier;
 
-use crate::{ir::Project, PathNode};
+use crate::{codegen, ir::Project, PathNode};
 
 #[derive(Debug, Clone)]
 struct Interface {
@@ -55,8 +55,22 @@ impl Display for ObjArg {
     }
 }
 
+/// Writes a JSDoc comment, if there is anything to say.
+fn write_doc(f: &mut std::fmt::Formatter<'_>, doc: Option<&String>) -> std::fmt::Result {
+    let Some(doc) = doc else {
+        return Ok(());
+    };
+
+    writeln!(f, "/**")?;
+    for line in doc.lines() {
+        writeln!(f, " * {}", line.replace("*/", "* /"))?;
+    }
+    writeln!(f, " */")
+}
+
 #[derive(Debug, Clone)]
 struct Method {
+    doc: Option<String>,
     ident: Ident,
     arguments: Vec<Param>,
     body: Body,
@@ -64,6 +78,7 @@ struct Method {
 
 impl Display for Method {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_doc(f, self.doc.as_ref())?;
         writeln!(
             f,
             "{}({}) {{ {} }}",
@@ -80,12 +95,14 @@ impl Display for Method {
 
 #[derive(Debug, Clone)]
 struct Getter {
+    doc: Option<String>,
     ident: Ident,
     body: Body,
 }
 
 impl Display for Getter {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_doc(f, self.doc.as_ref())?;
         writeln!(f, "get {}() {{ {} }}", self.ident, self.body)
     }
 }
@@ -156,6 +173,7 @@ impl Display for BundleGetter {
 
 #[derive(Debug, Clone)]
 struct Class {
+    doc: Option<String>,
     ident: Ident,
     exported: bool,
     implements: Vec<Ident>,
@@ -164,6 +182,7 @@ struct Class {
 
 impl Display for Class {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_doc(f, self.doc.as_ref())?;
         if self.exported {
             write!(f, "export ")?;
         }
@@ -262,72 +281,40 @@ pub fn generate(input: Project) -> Result<PathNode, ParserError> {
 
         for (_, m) in category.translation_units.iter() {
             let lang = m.locale.clone();
-            let resource = m.to_flt_resource(&category.descriptions)?;
+            let resource = m.to_flt_resource(&category);
 
             flts.push(Ast::Body(Body::Raw(Raw(dump_flt_inline(
                 &lang, &resource, is_core,
             )))));
         }
 
-        let strings = category.base_strings();
-        let resource = strings.to_flt_resource(&category.descriptions)?;
-
-        let ts_asts = resource
-            .body
+        let ts_asts = codegen::accessors(&category)
             .into_iter()
-            .filter_map(|ast| match ast {
-                fluent_syntax::ast::Entry::Message(x) if x.value.is_some() => {
-                    let name = x.id.name;
-
-                    let items = std::iter::once((name.clone(), None, x.value.unwrap())).chain(
-                        x.attributes
-                            .into_iter()
-                            .map(move |y| (name.clone(), Some(y.id.name.to_string()), y.value)),
-                    );
-                    Some(items)
-                }
-                _ => None,
-            })
-            .flatten()
-            .map(|(name, attr, value)| {
-                let vars = value
-                    .elements
+            .flat_map(|accessor| {
+                let section = accessor
+                    .section
+                    .as_ref()
+                    .map(|x| Ast::Body(Body::Raw(Raw(format!("// {}\n", x.replace('\n', " "))))));
+                let vars = accessor
+                    .variables
                     .iter()
-                    .filter_map(|x| match x {
-                        fluent_syntax::ast::PatternElement::Placeable { expression } => {
-                            Some(expression)
-                        }
-                        _ => None,
-                    })
-                    .map(|p| match p {
-                        fluent_syntax::ast::Expression::Select { selector, .. } => selector,
-                        fluent_syntax::ast::Expression::Inline(selector) => selector,
-                    })
-                    .filter_map(|p| match p {
-                        fluent_syntax::ast::InlineExpression::VariableReference { id } => {
-                            Some((Ident(id.name.to_lower_camel_case()), id))
-                        }
-                        _ => None,
-                    })
+                    .map(|(x, _)| (Ident(x.to_lower_camel_case()), x))
                     .collect::<Vec<_>>();
+                let ident = Ident(accessor.name().to_lower_camel_case());
 
-                let ident = if let Some(attr) = attr.as_deref() {
-                    Ident(format!("{name}__{attr}").to_lower_camel_case())
-                } else {
-                    Ident(format!("{name}").to_lower_camel_case())
-                };
-
-                if vars.is_empty() {
+                let ast = if vars.is_empty() {
                     Ast::Getter(Getter {
+                        doc: accessor.description,
                         ident,
                         body: Body::BundleGetter(BundleGetter {
-                            raw_id: name,
-                            attr,
+                            raw_id: accessor.id.to_string(),
+                            attr: accessor.attr.map(|x| x.to_string()),
                             args: vec![],
                         }),
                     })
                 } else {
                     Ast::Method(Method {
+                        doc: accessor.description,
                         ident,
                         arguments: vars
                             .iter()
@@ -337,19 +324,22 @@ pub fn generate(input: Project) -> Result<PathNode, ParserError> {
                             })
                             .collect(),
                         body: Body::BundleGetter(BundleGetter {
-                            raw_id: name,
-                            attr,
+                            raw_id: accessor.id.to_string(),
+                            attr: accessor.attr.map(|x| x.to_string()),
                             args: vars
                                 .iter()
                                 .map(|(camel, real)| ObjArg {
-                                    ident: Ident(format!("{:?}", real.name)),
+                                    ident: Ident(format!("{:?}", real)),
                                     value: camel.to_string(),
                                 })
                                 .collect(),
                         }),
                     })
-                }
-            });
+                };
+
+                section.into_iter().chain(std::iter::once(ast))
+            })
+            .collect::<Vec<_>>();
 
         let core_import = if is_core {
             ""
@@ -368,6 +358,7 @@ pub fn generate(input: Project) -> Result<PathNode, ParserError> {
         };
 
         let ts_ast = Class {
+            doc: category.notes.clone(),
             ident: Ident(module_name.to_pascal_case()),
             exported: true,
             implements: vec![],
@@ -0,0 +1,395 @@
+//! Apple String Catalogs (`.xcstrings`), one table per category.
+//!
+//! Attributes are stored as `<message>__<attribute>` keys, the same way
+//! [`crate::xlsx`] stores them as rows. A localization's state follows the
+//! status of its unit, and translations of a base string that has changed
+//! since need review.
+
+use std::{collections::BTreeMap, path::Path, str::FromStr};
+
+use fluent_syntax::parser::ParserError;
+use heck::{ToPascalCase, ToSnakeCase};
+use icu::locid::LanguageIdentifier;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    flt::render,
+    ir::{
+        metadata, Category, Keys, Pattern, Project, Status, TUIdentifier, TranslationUnit,
+        TranslationUnitMap,
+    },
+    plural::{self, PluralSelect},
+    printf, PathNode,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StringCatalog {
+    pub source_language: String,
+    #[serde(default)]
+    pub strings: BTreeMap<String, CatalogEntry>,
+    pub version: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CatalogEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extraction_state: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub localizations: BTreeMap<String, Localization>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Localization {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub string_unit: Option<StringUnit>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variations: Option<Variations>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StringUnit {
+    pub state: String,
+    pub value: String,
+}
+
+/// The state of a localization of `base`, the unit of the base string.
+fn state(unit: &TranslationUnit, base: &TranslationUnit) -> &'static str {
+    let outdated = unit
+        .metadata
+        .source_hash
+        .as_ref()
+        .is_some_and(|x| *x != metadata::source_hash(base));
+    match unit.metadata.status {
+        _ if outdated => "needs_review",
+        Some(Status::Untranslated) => "new",
+        Some(Status::MachineTranslated | Status::NeedsReview) => "needs_review",
+        Some(Status::Approved) | None => "translated",
+    }
+}
+
+/// The status a localization's state records, if any.
+fn status(state: &str) -> Option<Status> {
+    match state {
+        "new" => Some(Status::Untranslated),
+        "needs_review" => Some(Status::NeedsReview),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Variations {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub plural: BTreeMap<String, Variation>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Variation {
+    pub string_unit: StringUnit,
+}
+
+fn localization(
+    pattern: &Pattern,
+    state: &str,
+    units: &TranslationUnitMap,
+    args: &mut Vec<String>,
+) -> Localization {
+    let string_unit = |value| StringUnit {
+        state: state.to_string(),
+        value,
+    };
+    let format = |pattern: &Pattern, args: &mut Vec<String>, numbers: &[&str]| {
+        let segments = render::segments(pattern, units);
+        printf::format(&segments, args, numbers, printf::APPLE, str::to_string)
+    };
+
+    match PluralSelect::from_pattern(pattern) {
+        Some(select) => {
+            let plural = plural::cardinal_categories(&units.locale)
+                .iter()
+                .map(|category| {
+                    let value = format(&select.expand(category), args, &[select.variable]);
+                    (
+                        category.to_string(),
+                        Variation {
+                            string_unit: string_unit(value),
+                        },
+                    )
+                })
+                .collect();
+            Localization {
+                string_unit: None,
+                variations: Some(Variations { plural }),
+            }
+        }
+        None => Localization {
+            string_unit: Some(string_unit(format(pattern, args, &[]))),
+            variations: None,
+        },
+    }
+}
+
+fn catalog_key(key: &TUIdentifier, attr: Option<&TUIdentifier>) -> String {
+    match attr {
+        Some(attr) => format!("{}__{}", key, attr),
+        None => key.to_string(),
+    }
+}
+
+pub fn generate(input: Project) -> Result<PathNode, ParserError> {
+    let mut files = BTreeMap::new();
+
+    for (category_id, category) in input.categories.into_iter() {
+        let base = category.base_strings();
+        let mut strings = BTreeMap::new();
+
+        for (key, attr) in category.ordered_tu_identity_keys() {
+            // Terms only exist to be inlined into messages
+            if base[key].is_term() {
+                continue;
+            }
+
+            let base_args = printf::arguments(base, key, attr);
+
+            let mut localizations = BTreeMap::new();
+            for locale in category.ordered_locale_keys() {
+                let units = category.get(locale).unwrap();
+                let Some(unit) = units.get(key) else {
+                    continue;
+                };
+                let Some(value) = unit.value(attr) else {
+                    continue;
+                };
+
+                let mut args = base_args.clone();
+                let state = state(unit, &base[key]);
+                localizations.insert(
+                    locale.to_string(),
+                    localization(value, state, units, &mut args),
+                );
+            }
+
+            strings.insert(
+                catalog_key(key, attr),
+                CatalogEntry {
+                    comment: category.descriptions.get(key).cloned(),
+                    extraction_state: Some("manual".to_string()),
+                    localizations,
+                },
+            );
+        }
+
+        let catalog = StringCatalog {
+            source_language: category.default_locale.to_string(),
+            strings,
+            version: "1.0".to_string(),
+        };
+
+        files.insert(
+            format!("{}.xcstrings", category_id.to_pascal_case()),
+            PathNode::File(serde_json::to_string_pretty(&catalog).unwrap().into_bytes()),
+        );
+    }
+
+    Ok(PathNode::Directory(files))
+}
+
+impl Localization {
+    /// The state of the string unit, or of the `other` plural variation.
+    fn state(&self) -> Option<&str> {
+        let unit = match self.string_unit.as_ref() {
+            Some(unit) => unit,
+            None => {
+                let plural = &self.variations.as_ref()?.plural;
+                &plural
+                    .get("other")
+                    .or_else(|| plural.values().next())?
+                    .string_unit
+            }
+        };
+        Some(&unit.state)
+    }
+}
+
+fn to_pattern(localization: &Localization) -> Option<Pattern> {
+    if let Some(unit) = localization.string_unit.as_ref() {
+        return Some(printf::to_pattern(&unit.value));
+    }
+
+    let plural = &localization.variations.as_ref()?.plural;
+    let other = plural.get("other").or_else(|| plural.values().next())?;
+    let arg = printf::number_argument(&other.string_unit.value).unwrap_or(1);
+
+    let variants = plural::CATEGORIES
+        .iter()
+        .filter_map(|category| {
+            let value = &plural.get(*category)?.string_unit.value;
+            Some((category.to_string(), printf::to_pattern(value)))
+        })
+        .collect::<Vec<_>>();
+
+    Some(plural::select(&format!("arg{arg}"), variants))
+}
+
+/// Loads a single catalog into a category named after its file.
+pub fn load_catalog(path: &Path, keys: &mut Keys) -> anyhow::Result<Category> {
+    let name = path.file_stem().and_then(|x| x.to_str()).unwrap();
+    parse_catalog(name, &std::fs::read_to_string(path)?, keys)
+}
+
+fn parse_catalog(name: &str, source: &str, keys: &mut Keys) -> anyhow::Result<Category> {
+    let catalog: StringCatalog = serde_json::from_str(source)?;
+    let default_locale = LanguageIdentifier::from_str(&catalog.source_language)?;
+
+    let mut category = Category {
+        key: keys.category(&name.to_snake_case())?,
+        name: name.to_string(),
+        default_locale: default_locale.clone(),
+        descriptions: Default::default(),
+        notes: None,
+        sections: Default::default(),
+        translation_units: Default::default(),
+    };
+    category.insert(TranslationUnitMap::new(default_locale.clone()));
+
+    for (catalog_key, entry) in catalog.strings {
+        let mut chunks = catalog_key.splitn(2, "__");
+        let key = keys.unit(&category.key, chunks.next().unwrap())?;
+        let attr = chunks
+            .next()
+            .map(|x| keys.attribute(&category.key, &key, x))
+            .transpose()?;
+
+        if let Some(comment) = entry.comment.filter(|_| attr.is_none()) {
+            category.descriptions.insert(key.clone(), comment);
+        }
+
+        let mut values = entry
+            .localizations
+            .iter()
+            .filter_map(|(locale, x)| Some((locale.as_str(), to_pattern(x)?, x.state())))
+            .map(|(locale, x, state)| {
+                LanguageIdentifier::from_str(locale).map(|locale| (locale, x, state))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Xcode leaves out the source localization when the key doubles as
+        // the source string.
+        if !values
+            .iter()
+            .any(|(locale, _, _)| *locale == default_locale)
+        {
+            values.push((
+                default_locale.clone(),
+                printf::to_pattern(&catalog_key),
+                None,
+            ));
+        }
+
+        for (locale, value, state) in values {
+            let unit = category
+                .entry(locale.clone())
+                .or_insert_with(|| TranslationUnitMap::new(locale))
+                .entry(key.clone())
+                .or_insert_with(|| TranslationUnit::new(key.clone()));
+            if let Some(status) = state.and_then(status) {
+                unit.metadata.status = Some(status);
+            }
+
+            match attr.as_ref() {
+                Some(attr) => {
+                    unit.attributes.insert(attr.clone(), value);
+                }
+                None => unit.main = value,
+            }
+        }
+    }
+
+    Ok(category)
+}
+
+/// Loads a single `.xcstrings` file or every catalog in a directory.
+pub fn load_project_from_path(path: &Path, keys: &mut Keys) -> anyhow::Result<Project> {
+    let mut project = Project::default();
+
+    if path.is_file() {
+        project.insert(load_catalog(path, keys)?);
+        return Ok(project);
+    }
+
+    for entry in path.read_dir()?.filter_map(Result::ok) {
+        let path = entry.path();
+        if path.extension().and_then(|x| x.to_str()) == Some("xcstrings") {
+            project.insert(load_catalog(&path, keys)?);
+        }
+    }
+
+    Ok(project)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flt::{pattern_source, test_project};
+
+    #[test]
+    fn plural_round_trip() {
+        let mut project = test_project(&[
+            (
+                "en",
+                "files = { $count ->\n    [one] One file\n   *[other] { $count } files\n}\n",
+            ),
+            (
+                "ru",
+                concat!(
+                    "files = { $count ->\n",
+                    "    [one] { $count } файл\n",
+                    "    [few] { $count } файла\n",
+                    "   *[many] { $count } файлов\n",
+                    "}\n",
+                ),
+            ),
+        ]);
+        let core = project.categories.values_mut().next().unwrap();
+        let key = TUIdentifier::try_from("files").unwrap();
+        let ru = LanguageIdentifier::from_str("ru").unwrap();
+        core.get_mut(&ru)
+            .unwrap()
+            .get_mut(&key)
+            .unwrap()
+            .metadata
+            .status = Some(Status::NeedsReview);
+
+        let tree = generate(project).unwrap();
+        let category =
+            parse_catalog("Core", &tree.text("Core.xcstrings"), &mut Keys::strict()).unwrap();
+
+        let en = &category.base_strings()[&key];
+        assert_eq!(
+            pattern_source(&en.main),
+            "{ $arg1 ->\n    [one] One file\n   *[other] { NUMBER($arg1) } files\n}"
+        );
+        assert_eq!(en.metadata.status, None);
+
+        let ru = &category.get(&ru).unwrap()[&key];
+        assert_eq!(
+            pattern_source(&ru.main),
+            concat!(
+                "{ $arg1 ->\n",
+                "    [one] { NUMBER($arg1) } файл\n",
+                "    [few] { NUMBER($arg1) } файла\n",
+                "    [many] { NUMBER($arg1) } файлов\n",
+                "   *[other] { NUMBER($arg1) } файлов\n",
+                "}",
+            )
+        );
+        assert_eq!(ru.metadata.status, Some(Status::NeedsReview));
+    }
+}
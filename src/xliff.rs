@@ -0,0 +1,571 @@
+//! XLIFF 2.0 for CAT tools, one file per category and target locale.
+//!
+//! Everything in a pattern that is not plain text (placeables, and the
+//! structure of select expressions) is written as a protected `<ph>` inline
+//! code whose Fluent source is kept in `<originalData>`, so translators can
+//! only touch the text and importing the file back is lossless. Codes can't be
+//! copied or deleted, and the syntax of select expressions keeps its order.
+
+use std::{collections::BTreeMap, path::Path, str::FromStr};
+
+use fluent_syntax::parser::ParserError;
+use html_escape::{encode_double_quoted_attribute, encode_text};
+use icu::locid::LanguageIdentifier;
+use quick_xml::events::{BytesStart, Event};
+
+use crate::{
+    flt::{escape_text, parse_pattern, serializer},
+    ir::{
+        Category, Element, Keys, Pattern, Project, TUIdentifier, TranslationUnit,
+        TranslationUnitMap,
+    },
+    PathNode,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Token {
+    Text(String),
+    /// A placeable, such as `{ $count }`
+    Code(String),
+    /// The syntax of a select expression around its variants
+    Select(String),
+}
+
+fn push_token(out: &mut Vec<Token>, token: Token) {
+    match (out.last_mut(), token) {
+        (Some(Token::Text(a)), Token::Text(b)) => a.push_str(&b),
+        (Some(Token::Select(a)), Token::Select(b)) => a.push_str(&b),
+        (_, token) => out.push(token),
+    }
+}
+
+pub(crate) fn tokenize(pattern: &Pattern, out: &mut Vec<Token>) {
+    for element in pattern.elements.iter() {
+        match element {
+            Element::Text(value) => push_token(out, Token::Text(value.clone())),
+            Element::Placeable(expression) => push_token(
+                out,
+                Token::Code(format!(
+                    "{{ {} }}",
+                    serializer::serialize_inline_expression(&expression.to_fluent())
+                )),
+            ),
+            Element::Select(select) => {
+                push_token(
+                    out,
+                    Token::Select(format!(
+                        "{{ {} ->\n",
+                        serializer::serialize_inline_expression(&select.selector.to_fluent())
+                    )),
+                );
+                for variant in select.variants.iter() {
+                    let marker = if variant.default { "*" } else { "" };
+                    push_token(
+                        out,
+                        Token::Select(format!("{marker}[{}] ", variant.key.as_str())),
+                    );
+                    tokenize(&variant.value, out);
+                    push_token(out, Token::Select("\n".to_string()));
+                }
+                push_token(out, Token::Select("}".to_string()));
+            }
+        }
+    }
+}
+
+fn untokenize(tokens: &[Token]) -> Result<Pattern, ParserError> {
+    let source = tokens
+        .iter()
+        .map(|x| match x {
+            Token::Text(text) => escape_text(text),
+            Token::Code(code) | Token::Select(code) => code.clone(),
+        })
+        .collect::<String>();
+    parse_pattern(&source)
+}
+
+/// Inline codes of a unit, shared between its source and target.
+#[derive(Default)]
+struct OriginalData {
+    data: Vec<String>,
+}
+
+impl OriginalData {
+    fn data_ref(&mut self, code: &str) -> usize {
+        match self.data.iter().position(|x| x == code) {
+            Some(i) => i + 1,
+            None => {
+                self.data.push(code.to_string());
+                self.data.len()
+            }
+        }
+    }
+}
+
+/// Writes tokens as inline content. `ids` maps each code occurrence to its
+/// `<ph>` id, so that a target reuses the ids of the matching source codes.
+fn write_content(
+    tokens: &[Token],
+    data: &mut OriginalData,
+    ids: &mut Vec<(usize, usize)>,
+    next_id: &mut usize,
+) -> String {
+    let mut used = vec![];
+    let mut out = String::new();
+    let mut in_sequence = false;
+
+    for token in tokens {
+        match token {
+            Token::Text(text) => out.push_str(&encode_text(text)),
+            Token::Code(code) | Token::Select(code) => {
+                let data_ref = data.data_ref(code);
+                let id = match ids
+                    .iter()
+                    .find(|(r, id)| *r == data_ref && !used.contains(id))
+                {
+                    Some((_, id)) => *id,
+                    None => {
+                        *next_id += 1;
+                        ids.push((data_ref, *next_id));
+                        *next_id
+                    }
+                };
+                used.push(id);
+
+                // Placeables may move with the text, but select syntax is one
+                // sequence of codes that must stay in order
+                let reorder = match token {
+                    Token::Select(_) if in_sequence => " canReorder=\"no\"",
+                    Token::Select(_) => {
+                        in_sequence = true;
+                        " canReorder=\"firstNo\""
+                    }
+                    _ => "",
+                };
+                out.push_str(&format!(
+                    "<ph id=\"{id}\" dataRef=\"d{data_ref}\" canCopy=\"no\" canDelete=\"no\"{reorder} disp=\"{}\"/>",
+                    encode_double_quoted_attribute(code.trim())
+                ));
+            }
+        }
+    }
+
+    out
+}
+
+pub(crate) fn unit_id(key: &TUIdentifier, attr: Option<&TUIdentifier>) -> String {
+    match attr {
+        Some(attr) => format!("{}__{}", key, attr),
+        None => key.to_string(),
+    }
+}
+
+fn generate_file(category: &Category, target: &TranslationUnitMap) -> String {
+    let base = category.base_strings();
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(&format!(
+        "<xliff xmlns=\"urn:oasis:names:tc:xliff:document:2.0\" version=\"2.0\" srcLang=\"{}\" trgLang=\"{}\">\n",
+        base.locale, target.locale
+    ));
+    out.push_str(&format!(
+        "  <file id=\"{}\" original=\"{}\">\n",
+        encode_double_quoted_attribute(&category.key.to_string()),
+        encode_double_quoted_attribute(&category.name)
+    ));
+
+    for (key, attr) in category.ordered_tu_identity_keys() {
+        let Some(source) = base.get(key).and_then(|x| x.value(attr)) else {
+            continue;
+        };
+        let target = target.get(key).and_then(|x| x.value(attr));
+
+        let mut source_tokens = vec![];
+        tokenize(source, &mut source_tokens);
+        let target_tokens = target.map(|x| {
+            let mut tokens = vec![];
+            tokenize(x, &mut tokens);
+            tokens
+        });
+
+        let mut data = OriginalData::default();
+        let mut ids = vec![];
+        let mut next_id = 0;
+        let source = write_content(&source_tokens, &mut data, &mut ids, &mut next_id);
+        let target = target_tokens
+            .as_ref()
+            .map(|x| write_content(x, &mut data, &mut ids, &mut next_id));
+
+        out.push_str(&format!(
+            "    <unit id=\"{}\">\n",
+            encode_double_quoted_attribute(&unit_id(key, attr))
+        ));
+
+        if let Some(description) = category.descriptions.get(key) {
+            out.push_str(&format!(
+                "      <notes>\n        <note category=\"description\">{}</note>\n      </notes>\n",
+                encode_text(description)
+            ));
+        }
+
+        if !data.data.is_empty() {
+            out.push_str("      <originalData>\n");
+            for (i, code) in data.data.iter().enumerate() {
+                out.push_str(&format!(
+                    "        <data id=\"d{}\">{}</data>\n",
+                    i + 1,
+                    encode_text(code)
+                ));
+            }
+            out.push_str("      </originalData>\n");
+        }
+
+        let state = if target.is_some() {
+            "translated"
+        } else {
+            "initial"
+        };
+        out.push_str(&format!("      <segment state=\"{state}\">\n"));
+        out.push_str(&format!("        <source>{source}</source>\n"));
+        if let Some(target) = target {
+            out.push_str(&format!("        <target>{target}</target>\n"));
+        }
+        out.push_str("      </segment>\n    </unit>\n");
+    }
+
+    out.push_str("  </file>\n</xliff>\n");
+    out
+}
+
+pub fn generate(input: Project) -> Result<PathNode, ParserError> {
+    let mut files = BTreeMap::new();
+
+    for (category_id, category) in input.categories.iter() {
+        let mut subfiles = BTreeMap::new();
+
+        for (locale, target) in category.translation_units.iter() {
+            if *locale == category.default_locale {
+                continue;
+            }
+
+            subfiles.insert(
+                format!("{locale}.xlf"),
+                PathNode::File(generate_file(category, target).into_bytes()),
+            );
+        }
+
+        files.insert(category_id.to_string(), PathNode::Directory(subfiles));
+    }
+
+    Ok(PathNode::Directory(files))
+}
+
+fn attribute(e: &BytesStart, name: &str) -> anyhow::Result<Option<String>> {
+    Ok(match e.try_get_attribute(name)? {
+        Some(x) => Some(x.unescape_value()?.to_string()),
+        None => None,
+    })
+}
+
+#[derive(Debug, Default)]
+struct ParsedUnit {
+    id: String,
+    description: Option<String>,
+    data: BTreeMap<String, String>,
+    source: Vec<Token>,
+    target: Option<Vec<Token>>,
+}
+
+#[derive(Debug, Default)]
+pub struct ParsedFile {
+    source_locale: Option<LanguageIdentifier>,
+    target_locale: Option<LanguageIdentifier>,
+    category: Option<(String, Option<String>)>,
+    units: Vec<ParsedUnit>,
+}
+
+/// Inline references are resolved once the whole unit has been read, since
+/// `<originalData>` may come after the segments.
+#[derive(Debug, Clone)]
+enum RawToken {
+    Text(String),
+    Ref(String),
+}
+
+fn resolve(raw: Vec<RawToken>, data: &BTreeMap<String, String>) -> Vec<Token> {
+    let mut out = vec![];
+    for token in raw {
+        match token {
+            RawToken::Text(x) => push_token(&mut out, Token::Text(x)),
+            RawToken::Ref(x) => match data.get(&x) {
+                Some(code) => push_token(&mut out, Token::Code(code.clone())),
+                None => eprintln!("Unknown inline code reference: {x}"),
+            },
+        }
+    }
+    out
+}
+
+pub fn parse_file(xml: &str) -> anyhow::Result<ParsedFile> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut file = ParsedFile::default();
+
+    let mut unit: Option<ParsedUnit> = None;
+    let mut source: Option<Vec<RawToken>> = None;
+    let mut target: Option<Vec<RawToken>> = None;
+    // Which element's text is being read: a note, a data entry, or inline
+    // content of the source or target
+    let mut reading: Option<&'static str> = None;
+    let mut data_id: Option<String> = None;
+    let mut text = String::new();
+
+    loop {
+        let event = reader.read_event()?;
+        match &event {
+            Event::Eof => break,
+            Event::Start(e) | Event::Empty(e) => {
+                let is_empty = matches!(event, Event::Empty(_));
+                match e.local_name().as_ref() {
+                    b"xliff" => {
+                        file.source_locale = attribute(e, "srcLang")?
+                            .map(|x| LanguageIdentifier::from_str(&x))
+                            .transpose()?;
+                        file.target_locale = attribute(e, "trgLang")?
+                            .map(|x| LanguageIdentifier::from_str(&x))
+                            .transpose()?;
+                    }
+                    b"file" => {
+                        if let Some(id) = attribute(e, "id")? {
+                            file.category = Some((id, attribute(e, "original")?));
+                        }
+                    }
+                    b"unit" => {
+                        unit = Some(ParsedUnit {
+                            id: attribute(e, "id")?.unwrap_or_default(),
+                            ..Default::default()
+                        });
+                    }
+                    b"note" if attribute(e, "category")?.as_deref() == Some("description") => {
+                        if !is_empty {
+                            reading = Some("note");
+                        }
+                        text.clear();
+                    }
+                    b"data" => {
+                        if is_empty {
+                            if let (Some(unit), Some(id)) = (unit.as_mut(), attribute(e, "id")?) {
+                                unit.data.insert(id, String::new());
+                            }
+                        } else {
+                            reading = Some("data");
+                            data_id = attribute(e, "id")?;
+                            text.clear();
+                        }
+                    }
+                    b"source" => {
+                        source = Some(vec![]);
+                        if !is_empty {
+                            reading = Some("source");
+                        }
+                    }
+                    b"target" => {
+                        target = Some(vec![]);
+                        if !is_empty {
+                            reading = Some("target");
+                        }
+                    }
+                    b"ph" | b"sc" | b"ec" => {
+                        let tokens = match reading {
+                            Some("source") => source.as_mut(),
+                            Some("target") => target.as_mut(),
+                            _ => None,
+                        };
+                        if let (Some(tokens), Some(data_ref)) = (tokens, attribute(e, "dataRef")?) {
+                            tokens.push(RawToken::Ref(data_ref));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::Text(e) => {
+                let value = e.unescape()?;
+                match reading {
+                    Some("note") | Some("data") => text.push_str(&value),
+                    Some("source") => source
+                        .iter_mut()
+                        .for_each(|x| x.push(RawToken::Text(value.to_string()))),
+                    Some("target") => target
+                        .iter_mut()
+                        .for_each(|x| x.push(RawToken::Text(value.to_string()))),
+                    _ => {}
+                }
+            }
+            Event::End(e) => match e.local_name().as_ref() {
+                b"note" if reading == Some("note") => {
+                    if let Some(unit) = unit.as_mut() {
+                        unit.description = Some(std::mem::take(&mut text));
+                    }
+                    reading = None;
+                }
+                b"data" => {
+                    if let (Some(unit), Some(id)) = (unit.as_mut(), data_id.take()) {
+                        unit.data.insert(id, std::mem::take(&mut text));
+                    }
+                    reading = None;
+                }
+                b"source" | b"target" => reading = None,
+                b"unit" => {
+                    if let Some(mut unit) = unit.take() {
+                        unit.source = resolve(source.take().unwrap_or_default(), &unit.data);
+                        unit.target = target.take().map(|x| resolve(x, &unit.data));
+                        file.units.push(unit);
+                    }
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    Ok(file)
+}
+
+/// Merges the targets of every `.xlf` file below the given directory into
+/// the project the files were generated from. Units are matched by id; the
+/// base strings, descriptions and sections of the project are left as they
+/// are.
+pub fn merge_targets(project: &mut Project, path: &Path, keys: &mut Keys) -> anyhow::Result<()> {
+    let files = walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|x| {
+            x.path()
+                .extension()
+                .and_then(|x| x.to_str())
+                .map(|x| x == "xlf" || x == "xliff")
+                .unwrap_or(false)
+        });
+
+    for entry in files {
+        let file = parse_file(&std::fs::read_to_string(entry.path())?)?;
+        let Some((category_id, _)) = file.category else {
+            eprintln!("[{}] No file id found; skipping", entry.path().display());
+            continue;
+        };
+        let Some(locale) = file.target_locale else {
+            eprintln!(
+                "[{}] No target language found; skipping",
+                entry.path().display()
+            );
+            continue;
+        };
+
+        let category_id = keys.category(&category_id)?;
+        let Some(category) = project.get_mut(&category_id) else {
+            eprintln!(
+                "[{}] {} is not in the project; skipping",
+                locale, category_id
+            );
+            continue;
+        };
+
+        for unit in file.units {
+            let Some(target) = unit.target else {
+                continue;
+            };
+
+            let mut chunks = unit.id.splitn(2, "__");
+            let key = keys.unit(&category_id, chunks.next().unwrap())?;
+            let attr = chunks
+                .next()
+                .map(|x| keys.attribute(&category_id, &key, x))
+                .transpose()?;
+
+            let base = category.base_strings().get(&key);
+            if base.and_then(|x| x.value(attr.as_ref())).is_none() {
+                eprintln!(
+                    "[{}] {} is not in the base strings; skipping",
+                    locale, unit.id
+                );
+                continue;
+            }
+
+            let value = match untokenize(&target) {
+                Ok(x) => x,
+                Err(e) => {
+                    eprintln!("[{}] Could not parse {}: {}; skipping", locale, unit.id, e);
+                    continue;
+                }
+            };
+
+            let unit = category
+                .entry(locale.clone())
+                .or_insert_with(|| TranslationUnitMap::new(locale.clone()))
+                .entry(key.clone())
+                .or_insert_with(|| TranslationUnit::new(key));
+            match attr {
+                Some(attr) => {
+                    unit.attributes.insert(attr, value);
+                }
+                None => unit.main = value,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_empty_elements() {
+        let file = parse_file(concat!(
+            "<xliff version=\"2.0\" srcLang=\"en\" trgLang=\"de\">\n",
+            "  <file id=\"core\">\n",
+            "    <unit id=\"greet\">\n",
+            "      <notes><note category=\"description\"/></notes>\n",
+            "      <originalData><data id=\"d1\">{ $user }</data></originalData>\n",
+            "      <segment>\n",
+            "        <source/>\n",
+            "        <target>Hallo <ph id=\"1\" dataRef=\"d1\" canCopy=\"no\" canDelete=\"no\"/></target>\n",
+            "      </segment>\n",
+            "    </unit>\n",
+            "  </file>\n",
+            "</xliff>\n",
+        ))
+        .unwrap();
+
+        let unit = &file.units[0];
+        assert_eq!(unit.description, None);
+        assert!(unit.source.is_empty());
+        assert_eq!(
+            untokenize(unit.target.as_ref().unwrap()).unwrap(),
+            parse_pattern("Hallo { $user }").unwrap()
+        );
+    }
+
+    #[test]
+    fn protected_codes() {
+        let mut data = OriginalData::default();
+        let mut tokens = vec![];
+        tokenize(
+            &parse_pattern("{ $count ->\n [one] { $user }\n*[other] many\n} ok").unwrap(),
+            &mut tokens,
+        );
+
+        assert_eq!(
+            write_content(&tokens, &mut data, &mut vec![], &mut 0),
+            concat!(
+                "<ph id=\"1\" dataRef=\"d1\" canCopy=\"no\" canDelete=\"no\" ",
+                "canReorder=\"firstNo\" disp=\"{ $count -&gt;\n[one]\"/>",
+                "<ph id=\"2\" dataRef=\"d2\" canCopy=\"no\" canDelete=\"no\" disp=\"{ $user }\"/>",
+                "<ph id=\"3\" dataRef=\"d3\" canCopy=\"no\" canDelete=\"no\" ",
+                "canReorder=\"no\" disp=\"*[other]\"/>many",
+                "<ph id=\"4\" dataRef=\"d4\" canCopy=\"no\" canDelete=\"no\" ",
+                "canReorder=\"no\" disp=\"}\"/> ok",
+            )
+        );
+    }
+}
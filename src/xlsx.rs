@@ -4,13 +4,17 @@ use std::{
     str::FromStr,
 };
 
-use calamine::{Reader, Xlsx};
+use calamine::{Ods, Reader, Xlsx};
 use heck::ToSnakeCase;
 use icu::locid::LanguageIdentifier;
 use rust_xlsxwriter::{Format, FormatAlign, Workbook, XlsxError};
 
 use crate::{
-    ir::{CIdentifier, Category, Project, TUIdentifier, TranslationUnit, TranslationUnitMap},
+    flt::{parse_pattern_lossy, pattern_source},
+    ir::{
+        CIdentifier, Category, Keys, Metadata, Project, Section, TUIdentifier, TranslationUnit,
+        TranslationUnitMap,
+    },
     BTreeKeyedSet, PathNode,
 };
 
@@ -21,13 +25,28 @@ where
     type Error = anyhow::Error;
 
     fn try_from(value: Xlsx<T>) -> Result<Self, Self::Error> {
-        parse_xlsx(value)
+        parse_workbook(value, &mut Keys::strict())
     }
 }
 
-fn parse_xlsx<T>(mut workbook: Xlsx<T>) -> anyhow::Result<Project>
+impl<T> TryFrom<Ods<T>> for Project
 where
     T: Read + Seek,
+{
+    type Error = anyhow::Error;
+
+    fn try_from(value: Ods<T>) -> Result<Self, Self::Error> {
+        parse_workbook(value, &mut Keys::strict())
+    }
+}
+
+/// Parses every sheet of a workbook with [`parse_sheet`], whichever format
+/// calamine read it from. Categories are keyed by their sheet's name.
+pub fn parse_workbook<R, T>(mut workbook: R, keys: &mut Keys) -> anyhow::Result<Project>
+where
+    R: Reader<T>,
+    R::Error: std::error::Error + Send + Sync + 'static,
+    T: Read + Seek,
 {
     let sheets = workbook
         .worksheets()
@@ -41,264 +60,442 @@ where
 
     for sheet in sheets {
         let range = workbook.worksheet_range(&sheet).unwrap()?;
-        let mut rows = range.rows().enumerate();
-        let headers = rows.next().unwrap();
+        let rows = range
+            .rows()
+            .map(|row| row.iter().map(|x| x.as_string()).collect::<Vec<_>>());
 
-        // Collect the headers and their index
-        let Some(id_idx) = headers
-            .1
-            .iter()
-            .position(|x| x.as_string().as_deref() == Some("Identifier"))
-        else {
-            eprintln!("[{}] No identifier column found in sheet; skipping", sheet);
-            continue;
-        };
-        let Some(desc_idx) = headers
-            .1
+        let category_id = keys.category(&sheet.to_snake_case())?;
+        if let Some(category) = parse_sheet(&sheet, category_id, rows, keys)? {
+            categories.insert(category);
+        }
+    }
+
+    let project = Project {
+        categories,
+        ..Default::default()
+    };
+    Ok(project)
+}
+
+/// Parses a sheet laid out the way [`generate`] writes it: an `Identifier`
+/// column, a `Description` column and `Name (locale)` columns, the first of
+/// which holds the base strings. Attributes are `<message>__<attribute>`
+/// rows. Sheets without this layout are skipped.
+pub(crate) fn parse_sheet<I>(
+    sheet: &str,
+    key: CIdentifier,
+    rows: I,
+    keys: &mut Keys,
+) -> anyhow::Result<Option<Category>>
+where
+    I: IntoIterator<Item = Vec<Option<String>>>,
+{
+    let mut rows = rows.into_iter().enumerate();
+    let Some(headers) = rows.next() else {
+        eprintln!("[{}] No header row found in sheet; skipping", sheet);
+        return Ok(None);
+    };
+
+    // Collect the headers and their index
+    let Some(id_idx) = headers
+        .1
+        .iter()
+        .position(|x| x.as_deref() == Some("Identifier"))
+    else {
+        eprintln!("[{}] No identifier column found in sheet; skipping", sheet);
+        return Ok(None);
+    };
+    let Some(desc_idx) = headers
+        .1
+        .iter()
+        .position(|x| x.as_deref() == Some("Description"))
+    else {
+        eprintln!("[{}] No description column found in sheet; skipping", sheet);
+        return Ok(None);
+    };
+
+    // Collect columns with language codes
+    let lang_cols = headers
+        .1
+        .iter()
+        .enumerate()
+        .filter_map(|(i, x)| x.as_deref().map(|x| (i, x.trim().to_string())))
+        .filter_map(|(i, x)| x.split_whitespace().last().map(|x| (i, x.to_string())))
+        .filter(|(_, x)| x.starts_with('(') && x.ends_with(')'))
+        .map(|(i, x)| {
+            (
+                i,
+                x.trim_start_matches('(').trim_end_matches(')').to_string(),
+            )
+        })
+        .map(|(i, x)| LanguageIdentifier::from_str(&x).map(|x| (i, x)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Collect metadata columns as their field and language code
+    let metadata_cols = headers
+        .1
+        .iter()
+        .enumerate()
+        .filter_map(|(i, x)| {
+            let (field, locale) = x.as_deref()?.trim().strip_suffix(']')?.rsplit_once(" [")?;
+            let field = METADATA_COLUMNS.iter().position(|x| *x == field)?;
+            Some((i, field, locale.to_string()))
+        })
+        .map(|(i, field, x)| LanguageIdentifier::from_str(&x).map(|x| (i, field, x)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let Some((base_lang_idx, base_lang_code)) = lang_cols.first() else {
+        eprintln!("[{}] No base language found in sheet; skipping", sheet);
+        return Ok(None);
+    };
+
+    let mut descriptions = BTreeMap::new();
+    let mut notes = None;
+    let mut sections: Vec<Section> = vec![];
+    let mut in_section = false;
+
+    let mut languages = BTreeKeyedSet::from_set(
+        lang_cols
             .iter()
-            .position(|x| x.as_string().as_deref() == Some("Description"))
-        else {
-            eprintln!("[{}] No description column found in sheet; skipping", sheet);
+            .map(|(_, x)| TranslationUnitMap {
+                locale: x.clone(),
+                translation_units: Default::default(),
+            })
+            .collect::<BTreeSet<_>>(),
+        |x| x.locale.clone(),
+    );
+
+    for (row_idx, row) in rows {
+        let cell = |idx: usize| row.get(idx).cloned().flatten();
+
+        let Some(id) = cell(id_idx) else {
+            eprintln!(
+                "[{}] No identifier found at row {}; skipping",
+                &sheet, row_idx
+            );
             continue;
         };
 
-        // Collect columns with language codes
-        let lang_cols = headers
-            .1
-            .iter()
-            .enumerate()
-            .filter_map(|(i, x)| x.as_string().as_deref().map(|x| (i, x.trim().to_string())))
-            .filter_map(|(i, x)| x.split_whitespace().last().map(|x| (i, x.to_string())))
-            .filter(|(_, x)| x.starts_with('(') && x.ends_with(')'))
-            .map(|(i, x)| {
-                (
-                    i,
-                    x.trim_start_matches('(').trim_end_matches(')').to_string(),
-                )
-            })
-            .map(|(i, x)| LanguageIdentifier::from_str(&x).map(|x| (i, x)))
-            .collect::<Result<Vec<_>, _>>()?;
+        // Marker rows mirror Fluent's resource and group comments
+        match id.as_str() {
+            NOTES_MARKER => {
+                notes = cell(desc_idx);
+                continue;
+            }
+            SECTION_MARKER => {
+                let heading = cell(desc_idx).filter(|x| !x.trim().is_empty());
+                in_section = heading.is_some();
+                if let Some(heading) = heading {
+                    sections.push(Section {
+                        heading,
+                        keys: vec![],
+                    });
+                }
+                continue;
+            }
+            _ => {}
+        }
 
-        let Some((base_lang_idx, base_lang_code)) = lang_cols.first() else {
-            eprintln!("[{}] No base language found in sheet; skipping", sheet);
+        let mut chunks = id.split("__");
+        let id = keys.unit(&key, chunks.next().unwrap())?;
+        let meta_key = chunks
+            .next()
+            .map(|x| keys.attribute(&key, &id, x))
+            .transpose()?;
+
+        let Some(_base_str) = cell(*base_lang_idx) else {
+            eprintln!(
+                "[{}] No base string found at row {}; skipping",
+                &sheet, row_idx
+            );
             continue;
         };
 
-        let mut descriptions = BTreeMap::new();
-
-        let mut languages = BTreeKeyedSet::from_set(
-            lang_cols
-                .iter()
-                .map(|(_, x)| TranslationUnitMap {
-                    locale: x.clone(),
-                    translation_units: Default::default(),
-                })
-                .collect::<BTreeSet<_>>(),
-            |x| x.locale.clone(),
-        );
-
-        for (row_idx, row) in rows {
-            let Some(id) = row.get(id_idx).unwrap().as_string() else {
-                eprintln!(
-                    "[{}] No identifier found at row {}; skipping",
-                    &sheet, row_idx
-                );
-                continue;
-            };
-            let mut chunks = id.split("__");
-            let id = TUIdentifier::try_from(chunks.next().unwrap())?;
-            let meta_key = match chunks.next() {
-                Some(v) => Some(TUIdentifier::from_str(v)?),
-                None => None,
-            };
+        if let Some(desc) = cell(desc_idx) {
+            descriptions.insert(id.clone(), desc);
+        }
+        if in_section && meta_key.is_none() {
+            sections.last_mut().unwrap().keys.push(id.clone());
+        }
 
-            let Some(_base_str) = row.get(*base_lang_idx).unwrap().as_string() else {
-                eprintln!(
-                    "[{}] No base string found at row {}; skipping",
-                    &sheet, row_idx
-                );
-                continue;
+        for (col_idx, col_code) in lang_cols.iter() {
+            let col_str = match cell(*col_idx).filter(|x| !x.trim().is_empty()) {
+                Some(v) => v,
+                None => continue,
             };
 
-            if let Some(desc) = row.get(desc_idx).unwrap().as_string() {
-                descriptions.insert(id.clone(), desc);
-            }
-
-            for (col_idx, col_code) in lang_cols.iter() {
-                let col_str = match row
-                    .get(*col_idx)
+            if let Some(meta_key) = meta_key.as_ref() {
+                let strings = languages
+                    .get_mut(col_code)
                     .unwrap()
-                    .as_string()
-                    .filter(|x| !x.trim().is_empty())
-                {
+                    .translation_units
+                    .get_mut(&id);
+                let strings = match strings {
                     Some(v) => v,
-                    None => continue,
+                    None => {
+                        eprintln!(
+                            "[{}] No parent string found for attribute at row {}; skipping",
+                            &sheet, row_idx
+                        );
+                        continue;
+                    }
                 };
 
-                if let Some(meta_key) = meta_key.as_ref() {
-                    let strings = languages
-                        .get_mut(col_code)
-                        .unwrap()
-                        .translation_units
-                        .get_mut(&id);
-                    let strings = match strings {
-                        Some(v) => v,
-                        None => {
-                            eprintln!(
-                                "[{}] No parent string found for attribute at row {}; skipping",
-                                &sheet, row_idx
-                            );
-                            continue;
-                        }
-                    };
-
-                    strings.attributes.insert(meta_key.clone(), col_str);
-                } else {
-                    let data = TranslationUnit {
-                        key: id.clone(),
-                        main: col_str.to_string(),
-                        attributes: Default::default(),
-                    };
-                    languages
-                        .get_mut(col_code)
-                        .unwrap()
-                        .translation_units
-                        .insert(data);
-                }
+                strings
+                    .attributes
+                    .insert(meta_key.clone(), parse_pattern_lossy(&col_str));
+            } else {
+                let data = TranslationUnit {
+                    main: parse_pattern_lossy(&col_str),
+                    ..TranslationUnit::new(id.clone())
+                };
+                languages
+                    .get_mut(col_code)
+                    .unwrap()
+                    .translation_units
+                    .insert(data);
             }
         }
 
-        categories.insert(Category {
-            key: CIdentifier::try_from(sheet.to_snake_case()).unwrap(),
-            descriptions,
-            name: sheet.to_string(),
-            default_locale: base_lang_code.clone(),
-            translation_units: languages,
-        });
+        if meta_key.is_some() {
+            continue;
+        }
+        for (col_idx, field, locale) in metadata_cols.iter() {
+            let Some(value) = cell(*col_idx).filter(|x| !x.trim().is_empty()) else {
+                continue;
+            };
+            let Some(unit) = languages
+                .get_mut(locale)
+                .and_then(|x| x.translation_units.get_mut(&id))
+            else {
+                continue;
+            };
+            if let Err(e) = set_metadata_cell(&mut unit.metadata, *field, value) {
+                eprintln!("[{}] {} at row {}; skipping", &sheet, e, row_idx);
+            }
+        }
     }
 
-    let project = Project {
-        categories,
-        ..Default::default()
-    };
-    Ok(project)
+    Ok(Some(Category {
+        key,
+        descriptions,
+        notes,
+        sections,
+        name: sheet.to_string(),
+        default_locale: base_lang_code.clone(),
+        translation_units: languages,
+    }))
 }
 
 const COL_WIDTH: f64 = 30.0;
 
-fn generate_worksheet(workbook: &mut Workbook, category: &Category) -> Result<(), XlsxError> {
-    let sheet = workbook.add_worksheet();
-    sheet.set_name(&category.name)?;
+/// The headers of a locale's metadata columns, followed by ` [<locale>]`.
+const METADATA_COLUMNS: [&str; 4] = ["Status", "Translator", "Modified", "Source hash"];
 
-    let mut col = 0u16;
-    let mut row = 0u32;
+fn metadata_cells(metadata: &Metadata) -> [Option<String>; 4] {
+    [
+        metadata.status.map(|x| x.to_string()),
+        metadata.translator.clone(),
+        metadata.modified.clone(),
+        metadata.source_hash.clone(),
+    ]
+}
 
-    let header_format = Format::new()
-        .set_bold()
-        .set_font_size(8)
-        .set_text_wrap()
-        .set_align(FormatAlign::VerticalCenter);
-    let id_format = Format::new()
-        .set_font_name("Roboto Mono")
-        .set_font_size(8)
-        .set_text_wrap()
-        .set_align(FormatAlign::VerticalCenter);
-    let text_wrap_format = Format::new().set_text_wrap().set_align(FormatAlign::Top);
+fn set_metadata_cell(metadata: &mut Metadata, column: usize, value: String) -> anyhow::Result<()> {
+    match column {
+        0 => metadata.status = Some(value.parse()?),
+        1 => metadata.translator = Some(value),
+        2 => metadata.modified = Some(value),
+        _ => metadata.source_hash = Some(value),
+    }
+    Ok(())
+}
 
-    sheet.write_string_with_format(row, col, "Identifier", &header_format)?;
-    sheet.set_column_width(col, COL_WIDTH)?;
-    sheet.set_column_format(col, &id_format)?;
-    col += 1;
-    sheet.write_string_with_format(row, col, "Description", &header_format)?;
-    sheet.set_column_width(col, COL_WIDTH)?;
-    sheet.set_column_format(col, &text_wrap_format)?;
-    col += 1;
+/// The identifier of the row holding the category's notes.
+const NOTES_MARKER: &str = "###";
+/// The identifier of a row starting a section, headed by its description.
+const SECTION_MARKER: &str = "##";
 
-    let autonym = category.default_locale.to_string();
+/// The header of a language column, e.g. `Deutsch (de)`.
+fn language_title(locale: &LanguageIdentifier) -> String {
+    let autonym = locale.to_string();
     let autonym = iso639::autonym::get(&autonym)
         .and_then(|x| x.autonym)
         .unwrap_or(&*autonym);
-    let title = format!("{} ({})", autonym, category.default_locale);
-    sheet.write_string_with_format(row, col, title, &header_format)?;
-    sheet.set_column_width(col, COL_WIDTH)?;
-    sheet.set_column_format(col, &text_wrap_format)?;
-    col += 1;
-
-    for map in category.values() {
-        if map.locale == category.default_locale {
-            continue;
-        }
-        let autonym = map.locale.to_string();
-        let autonym = iso639::autonym::get(&autonym)
-            .and_then(|x| x.autonym)
-            .unwrap_or(&*autonym);
-        let title = format!("{} ({})", autonym, map.locale);
-        sheet.write_string_with_format(row, col, title, &header_format)?;
-        sheet.set_column_width(col, COL_WIDTH)?;
-        col += 1;
-    }
-
-    sheet.set_freeze_panes(1, 2)?;
-    row += 1;
-    col = 0;
+    format!("{} ({})", autonym, locale)
+}
 
-    let tu = category.ordered_tu_identity_keys();
+/// The cells of a category's sheet, starting with the header row, in the
+/// layout [`parse_sheet`] reads back.
+pub(crate) fn sheet_rows(category: &Category) -> Vec<Vec<Option<String>>> {
+    let mut header = vec![
+        Some("Identifier".to_string()),
+        Some("Description".to_string()),
+    ];
+    header.extend(
+        category
+            .ordered_locale_keys()
+            .map(|x| Some(language_title(x))),
+    );
+
+    let mut rows = vec![header];
     let mut index_map = HashMap::new();
 
-    let mut i = 1u32;
-    for (id, attr) in tu {
+    if let Some(notes) = category.notes.as_ref() {
+        rows.push(vec![Some(NOTES_MARKER.to_string()), Some(notes.clone())]);
+    }
+
+    let base = category.base_strings();
+    for (id, attr) in category.ordered_tu_identity_keys() {
+        if let Some(section) = category
+            .section_starting_at(base, id)
+            .filter(|_| attr.is_none())
+        {
+            rows.push(vec![
+                Some(SECTION_MARKER.to_string()),
+                Some(section.heading.clone()),
+            ]);
+        }
+
         let identifier = if let Some(attr) = attr {
             format!("{}__{}", id, attr)
         } else {
             id.to_string()
         };
 
-        sheet.write_string_with_format(row, col, identifier, &id_format)?;
-        if let Some(desc) = category.descriptions.get(id) {
-            col += 1;
-            sheet.write_string_with_format(row, col, desc, &id_format)?;
-        }
-        col = 0;
-        row += 1;
-
-        index_map.insert((id, attr), i);
-        i += 1;
+        index_map.insert((id, attr), rows.len());
+        rows.push(vec![
+            Some(identifier),
+            category.descriptions.get(id).cloned(),
+        ]);
     }
 
-    // Reset the "cursor"
-    col = 2;
+    let set_cell = |row: &mut Vec<Option<String>>, col: usize, value: &str| {
+        if row.len() <= col {
+            row.resize(col + 1, None);
+        }
+        row[col] = Some(value.to_string());
+    };
 
-    for locale in category.ordered_locale_keys() {
-        let map = category.get(&locale).unwrap();
+    // Language columns start after the identifier and description
+    for (col, locale) in category.ordered_locale_keys().enumerate() {
+        let col = col + 2;
+        let map = category.get(locale).unwrap();
         for (id, tu) in map.iter() {
             let index = *index_map.get(&(id, None)).unwrap();
-            sheet.write_string_with_format(index, col, &tu.main, &text_wrap_format)?;
+            set_cell(&mut rows[index], col, &pattern_source(&tu.main));
 
             for (attr, v) in tu.attributes.iter() {
                 let index = *index_map.get(&(id, Some(attr))).unwrap();
-                sheet.write_string_with_format(index, col, v, &text_wrap_format)?;
+                set_cell(&mut rows[index], col, &pattern_source(v));
             }
         }
-        col += 1;
     }
 
-    Ok(())
+    // Metadata columns follow the languages, for locales that have any
+    let mut col = rows[0].len();
+    for locale in category.ordered_locale_keys() {
+        let map = category.get(locale).unwrap();
+        if map.values().all(|x| x.metadata.is_empty()) {
+            continue;
+        }
+
+        for (i, field) in METADATA_COLUMNS.iter().enumerate() {
+            set_cell(&mut rows[0], col + i, &format!("{field} [{locale}]"));
+        }
+        for (id, tu) in map.iter() {
+            let index = *index_map.get(&(id, None)).unwrap();
+            for (i, value) in metadata_cells(&tu.metadata).into_iter().enumerate() {
+                if let Some(value) = value {
+                    set_cell(&mut rows[index], col + i, &value);
+                }
+            }
+        }
+        col += METADATA_COLUMNS.len();
+    }
+
+    rows
 }
 
-pub fn generate(project: Project) -> Result<PathNode, XlsxError> {
-    let mut workbook = rust_xlsxwriter::Workbook::new();
+fn generate_worksheet(workbook: &mut Workbook, category: &Category) -> Result<(), XlsxError> {
+    let sheet = workbook.add_worksheet();
+    sheet.set_name(&category.name)?;
 
-    if let Some(core) = project
-        .categories
-        .get(&CIdentifier::try_from("core").unwrap())
-    {
-        generate_worksheet(&mut workbook, core)?;
+    let header_format = Format::new()
+        .set_bold()
+        .set_font_size(8)
+        .set_text_wrap()
+        .set_align(FormatAlign::VerticalCenter);
+    let id_format = Format::new()
+        .set_font_name("Roboto Mono")
+        .set_font_size(8)
+        .set_text_wrap()
+        .set_align(FormatAlign::VerticalCenter);
+    let marker_format = id_format.clone().set_bold();
+    // Terms only exist to be referenced from other rows
+    let term_format = id_format.clone().set_italic();
+    let text_wrap_format = Format::new().set_text_wrap().set_align(FormatAlign::Top);
+
+    let rows = sheet_rows(category);
+    let base = category.base_strings();
+
+    for col in 0..rows[0].len() as u16 {
+        sheet.set_column_width(col, COL_WIDTH)?;
+        if col == 0 {
+            sheet.set_column_format(col, &id_format)?;
+        } else if col <= 2 {
+            sheet.set_column_format(col, &text_wrap_format)?;
+        }
     }
 
-    for category in project.categories.values() {
-        if category.name == "Core" {
-            continue;
+    for (row, cells) in rows.iter().enumerate() {
+        let id = cells[0].as_deref().unwrap_or_default();
+        let is_marker = id == NOTES_MARKER || id == SECTION_MARKER;
+        let is_term = TUIdentifier::try_from(id.split("__").next().unwrap())
+            .ok()
+            .and_then(|x| base.get(&x))
+            .is_some_and(|x| x.is_term());
+
+        for (col, cell) in cells.iter().enumerate() {
+            let Some(cell) = cell else {
+                continue;
+            };
+            let format = match (row, col) {
+                (0, _) => &header_format,
+                (_, 0 | 1) if is_marker => &marker_format,
+                (_, 0 | 1) if is_term => &term_format,
+                (_, 0 | 1) => &id_format,
+                _ => &text_wrap_format,
+            };
+            sheet.write_string_with_format(row as u32, col as u16, cell, format)?;
         }
+    }
+
+    sheet.set_freeze_panes(1, 2)?;
+
+    Ok(())
+}
+
+/// The categories in the order of their sheets, with `core` first.
+pub(crate) fn sheet_order(project: &Project) -> Vec<&Category> {
+    let core = project
+        .categories
+        .get(&CIdentifier::try_from("core").unwrap());
+
+    core.into_iter()
+        .chain(
+            project
+                .categories
+                .values()
+                .filter(|category| category.name != "Core"),
+        )
+        .collect()
+}
+
+pub fn generate(project: Project) -> Result<PathNode, XlsxError> {
+    let mut workbook = rust_xlsxwriter::Workbook::new();
+
+    for category in sheet_order(&project) {
         generate_worksheet(&mut workbook, category)?;
     }
 